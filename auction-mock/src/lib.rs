@@ -14,6 +14,11 @@ imports!();
 
 use node_storage::types::bls_key::*;
 
+const BLS_STATUS_STAKED: &[u8] = b"staked";
+const BLS_STATUS_UNSTAKED: &[u8] = b"unStaked";
+const BLS_STATUS_NOT_STAKED: &[u8] = b"notStaked";
+const BLS_STATUS_JAILED: &[u8] = b"jailed";
+
 #[elrond_wasm_derive::contract(AuctionMockImpl)]
 pub trait AuctionMock {
     #[module(AuctionMockStorageImpl)]
@@ -57,10 +62,45 @@ pub trait AuctionMock {
             if err_code > 0 {
                 result_err_data.push(bls_key.clone());
                 result_err_data.push([err_code].to_vec());
+            } else {
+                self.storage().set_bls_status(bls_key, BLS_STATUS_STAKED);
             }
         }
 
         self.storage().set_num_nodes(new_num_nodes);
+        let mut total_staked = self.storage().get_mut_total_staked();
+        *total_staked += payment;
+
+        Ok(result_err_data.into())
+    }
+
+    #[payable]
+    #[endpoint(topUp)]
+    fn top_up_endpoint(
+        &self,
+        #[var_args] bls_keys: VarArgs<Vec<u8>>,
+        #[payment] payment: &BigUint,
+    ) -> SCResult<MultiResultVec<Vec<u8>>> {
+        require!(
+            !self.storage().is_staking_failure(),
+            "auction smart contract deliberate error"
+        );
+
+        require!(*payment > 0, "top up payment required");
+
+        let mut result_err_data: Vec<Vec<u8>> = Vec::new();
+        for (n, bls_key) in bls_keys.iter().enumerate() {
+            self.storage().set_topUp_bls_key(n, bls_key);
+
+            let err_code = self.storage().get_bls_deliberate_error(bls_key);
+            if err_code > 0 {
+                result_err_data.push(bls_key.clone());
+                result_err_data.push([err_code].to_vec());
+            }
+        }
+
+        let mut total_staked = self.storage().get_mut_total_staked();
+        *total_staked += payment;
 
         Ok(result_err_data.into())
     }
@@ -83,6 +123,8 @@ pub trait AuctionMock {
             if err_code > 0 {
                 result_err_data.push(bls_key.clone());
                 result_err_data.push([err_code].to_vec());
+            } else {
+                self.storage().set_bls_status(bls_key, BLS_STATUS_UNSTAKED);
             }
         }
 
@@ -107,15 +149,43 @@ pub trait AuctionMock {
             if err_code > 0 {
                 result_err_data.push(bls_key.clone());
                 result_err_data.push([err_code].to_vec());
+            } else {
+                self.storage()
+                    .set_bls_status(bls_key, BLS_STATUS_NOT_STAKED);
             }
         }
 
         let unbond_stake = BigUint::from(bls_keys.len()) * self.storage().get_stake_per_node();
+        let mut total_staked = self.storage().get_mut_total_staked();
+        if *total_staked >= unbond_stake {
+            *total_staked -= &unbond_stake;
+        }
         self.send_tx(&self.get_caller(), &unbond_stake, "unbond stake");
 
         Ok(result_err_data.into())
     }
 
+    #[endpoint(getBlsKeysStatus)]
+    fn get_bls_keys_status(
+        &self,
+        #[var_args] bls_keys: VarArgs<Vec<u8>>,
+    ) -> MultiResultVec<MultiResult2<Vec<u8>, Vec<u8>>> {
+        let mut result = Vec::new();
+        for bls_key in bls_keys.into_vec().into_iter() {
+            let mut status = self.storage().get_bls_status(&bls_key);
+            if status.is_empty() {
+                status = BLS_STATUS_NOT_STAKED.to_vec();
+            }
+            result.push(MultiResult2::from((bls_key, status)));
+        }
+        result.into()
+    }
+
+    #[endpoint(getTotalStaked)]
+    fn get_total_staked(&self) -> BigUint {
+        self.storage().get_total_staked()
+    }
+
     #[endpoint]
     fn claim(&self) -> SCResult<()> {
         Ok(())
@@ -128,7 +198,20 @@ pub trait AuctionMock {
         #[var_args] bls_keys: VarArgs<BLSKey>,
         #[payment] _fine_payment: BigUint,
     ) -> SCResult<()> {
-        self.storage().set_unjailed(&bls_keys.into_vec());
+        let bls_keys = bls_keys.into_vec();
+        for bls_key in bls_keys.iter() {
+            self.storage()
+                .set_bls_status(bls_key.to_vec().as_slice(), BLS_STATUS_STAKED);
+        }
+        self.storage().set_unjailed(&bls_keys);
         Ok(())
     }
+
+    /// Simulates the protocol jailing nodes.
+    #[endpoint]
+    fn jail(&self, #[var_args] bls_keys: VarArgs<Vec<u8>>) {
+        for bls_key in bls_keys.iter() {
+            self.storage().set_bls_status(bls_key, BLS_STATUS_JAILED);
+        }
+    }
 }
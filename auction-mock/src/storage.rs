@@ -21,12 +21,27 @@ pub trait AuctionMockStorage {
     #[storage_set("stake_bls_sig")]
     fn set_stake_bls_signature(&self, node_index: usize, bls_signature: &[u8]);
 
+    #[storage_set("topUp_bls_key")]
+    fn set_topUp_bls_key(&self, node_index: usize, bls_key: &[u8]);
+
     #[storage_set("unStake_bls_key")]
     fn set_unStake_bls_key(&self, node_index: usize, bls_key: &[u8]);
 
     #[storage_set("unBond_bls_key")]
     fn set_unBond_bls_key(&self, node_index: usize, bls_key: &[u8]);
 
+    #[storage_get("bls_status")]
+    fn get_bls_status(&self, bls_key: &[u8]) -> Vec<u8>;
+
+    #[storage_set("bls_status")]
+    fn set_bls_status(&self, bls_key: &[u8], status: &[u8]);
+
+    #[storage_get("total_staked")]
+    fn get_total_staked(&self) -> BigUint;
+
+    #[storage_get_mut("total_staked")]
+    fn get_mut_total_staked(&self) -> mut_storage!(BigUint);
+
     #[storage_set("staking_failure")]
     fn setStakingFailure(&self, will_fail: bool);
 
@@ -14,6 +14,16 @@ pub trait Auction {
         #[payment] payment: &BigUint,
     );
 
+    #[payable]
+    #[callback(auction_top_up_callback)]
+    fn topUp(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] amount_per_node: BigUint,
+        #[var_args] bls_keys: VarArgs<BLSKey>,
+        #[payment] payment: &BigUint,
+    );
+
     #[callback(auction_unstake_callback)]
     fn unStake(&self, #[callback_arg] node_ids: Vec<usize>, #[var_args] bls_keys: VarArgs<BLSKey>);
 
@@ -24,8 +34,27 @@ pub trait Auction {
         #[var_args] bls_keys_signatures: VarArgs<BLSKey>,
     );
 
+    #[callback(auction_claim_callback)]
     fn claim(&self);
 
+    #[callback(auction_bls_keys_status_callback)]
+    fn getBlsKeysStatus(
+        &self,
+        #[callback_arg] fix_mismatches: bool,
+        #[var_args] bls_keys: VarArgs<BLSKey>,
+    );
+
+    #[callback(auction_total_staked_callback)]
+    fn getTotalStaked(&self);
+
     #[payable]
-    fn unJail(&self, #[var_args] bls_keys: VarArgs<BLSKey>, #[payment] fine_payment: &BigUint);
+    #[callback(auction_unjail_callback)]
+    fn unJail(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] fine_payment: BigUint,
+        #[callback_arg] fine_payer: Address,
+        #[var_args] bls_keys: VarArgs<BLSKey>,
+        #[payment] payment: &BigUint,
+    );
 }
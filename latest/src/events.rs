@@ -1,5 +1,7 @@
 imports!();
 
+use super::node_storage::types::*;
+
 /// Contains all events that can be emitted by the contract.
 #[elrond_wasm_derive::module(EventsModuleImpl)]
 pub trait EventsModule {
@@ -9,24 +11,120 @@ pub trait EventsModule {
     #[event("0x0000000000000000000000000000000000000000000000000000000000000002")]
     fn unstake_event(&self, delegator: &Address, amount: &BigUint);
 
+    /// Data: BLS keys of the nodes that were activated, each with its new state.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000003")]
-    fn stake_node_ok_event(&self, _data: ());
+    fn stake_node_ok_event(&self, nodes: &[(BLSKey, NodeState)]);
 
+    /// Data: the error message, then the BLS keys of the nodes that failed,
+    /// each with the state it was reverted to and the error code returned by the auction SC.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000004")]
-    fn stake_node_fail_event(&self, _reason: &[u8]);
+    fn stake_node_fail_event(&self, reason_and_nodes: &(&[u8], Vec<(BLSKey, NodeState, i32)>));
 
+    /// Data: BLS keys of the nodes that were deactivated, each with its new state.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000005")]
-    fn unstake_node_ok_event(&self, _data: ());
+    fn unstake_node_ok_event(&self, nodes: &[(BLSKey, NodeState)]);
 
+    /// Data: same format as `stake_node_fail_event`.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000006")]
-    fn unstake_node_fail_event(&self, _reason: &[u8]);
+    fn unstake_node_fail_event(&self, reason_and_nodes: &(&[u8], Vec<(BLSKey, NodeState, i32)>));
 
+    /// Data: BLS keys of the nodes that were unbonded, each with its new state.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000007")]
-    fn unbond_node_ok_event(&self, _data: ());
+    fn unbond_node_ok_event(&self, nodes: &[(BLSKey, NodeState)]);
 
+    /// Data: same format as `stake_node_fail_event`.
     #[event("0x0000000000000000000000000000000000000000000000000000000000000008")]
-    fn unbond_node_fail_event(&self, _reason: &[u8]);
+    fn unbond_node_fail_event(&self, reason_and_nodes: &(&[u8], Vec<(BLSKey, NodeState, i32)>));
 
     #[event("0x0000000000000000000000000000000000000000000000000000000000000009")]
     fn claim_rewards_event(&self, user: &Address, amount: &BigUint);
+
+    /// Data: the error message, then the BLS keys of the nodes whose callback could not be processed.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000a")]
+    fn callback_error_event(&self, reason_and_bls_keys: &(&[u8], Vec<BLSKey>));
+
+    /// Data: BLS keys of the nodes forced out of a pending state by the owner, each with its new state.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000b")]
+    fn recover_pending_nodes_event(&self, nodes: &[(BLSKey, NodeState)]);
+
+    /// Topic: the amount each node was topped up with.
+    /// Data: BLS keys of the nodes that were topped up, each with its new stake.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000c")]
+    fn top_up_node_ok_event(&self, amount_per_node: &BigUint, nodes: &[(BLSKey, BigUint)]);
+
+    /// Data: the error message, then the BLS keys of the nodes that could not be topped up,
+    /// each with the error code returned by the auction SC.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000d")]
+    fn top_up_node_fail_event(&self, reason_and_nodes: &(&[u8], Vec<(BLSKey, i32)>));
+
+    /// Data: whether the mismatches were fixed, then the BLS keys of the nodes whose state
+    /// does not match the auction SC, each with the contract state and the state according to the auction SC.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000e")]
+    fn node_state_mismatch_event(
+        &self,
+        fixed_and_mismatches: &(bool, Vec<(BLSKey, NodeState, NodeState)>),
+    );
+
+    /// Topic: total stake according to the auction SC.
+    /// Data: total node stake according to the contract.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000000f")]
+    fn auction_total_staked_event(
+        &self,
+        auction_total_staked: &BigUint,
+        total_node_stake: &BigUint,
+    );
+
+    /// Data: stake received back from the auction SC after unbonding nodes.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000010")]
+    fn unbond_funds_returned_event(&self, amount: &BigUint);
+
+    /// Data: unused funds received back from the auction SC.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000011")]
+    fn claim_unused_funds_event(&self, amount: &BigUint);
+
+    /// Data: BLS keys of the nodes marked as jailed, each with its new state.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000012")]
+    fn jail_nodes_event(&self, nodes: &[(BLSKey, NodeState)]);
+
+    /// Topic: the fine paid.
+    /// Data: BLS keys of the nodes that were unjailed, each with its new state.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000013")]
+    fn unjail_node_ok_event(&self, fine_payment: &BigUint, nodes: &[(BLSKey, NodeState)]);
+
+    /// Data: the error message and error code returned by the auction SC,
+    /// then the BLS keys of the nodes that could not be unjailed.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000014")]
+    fn unjail_node_fail_event(&self, reason_and_bls_keys: &(&[u8], i32, Vec<BLSKey>));
+
+    // 0x15 and 0x16 are the operator role events, declared in the operators module.
+
+    /// Topic: the current owner.
+    /// Data: the proposed new owner.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000017")]
+    fn ownership_transfer_proposed_event(&self, owner: &Address, new_owner: &Address);
+
+    /// Topic: the previous owner.
+    /// Data: the new owner.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000018")]
+    fn ownership_transferred_event(&self, old_owner: &Address, new_owner: &Address);
+
+    /// Data: the scheduled service fee and the block nonce from which it can be applied.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000019")]
+    fn service_fee_change_scheduled_event(&self, service_fee_and_activation_nonce: &(usize, u64));
+
+    #[event("0x000000000000000000000000000000000000000000000000000000000000001a")]
+    fn re_delegate_rewards_event(&self, delegator: &Address, amount: &BigUint);
+
+    /// Data: the rounding dust added to the owner rewards.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000001b")]
+    fn dust_swept_event(&self, amount: &BigUint);
+
+    /// Data: the block nonce from which the scheduled service fee tiers can be applied.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000001d")]
+    fn service_fee_tiers_change_scheduled_event(&self, activation_nonce: &u64);
+
+    /// Topic: the delegator whose rewards could not be re-delegated on their behalf.
+    /// Data: their unclaimed rewards, which they can still claim.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000001e")]
+    fn claim_rewards_skipped_event(&self, delegator: &Address, unclaimed_rewards: &BigUint);
 }
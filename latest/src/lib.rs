@@ -8,10 +8,13 @@ pub mod auction_proxy;
 // modules
 pub mod events;
 pub mod node_activation;
+pub mod ownership;
 pub mod reset_checkpoint_types;
 pub mod reset_checkpoints;
+pub mod reward_history;
 pub mod rewards;
 pub mod settings;
+pub mod time_weighted_rewards;
 pub mod user_stake;
 pub mod user_unstake;
 
@@ -20,9 +23,12 @@ extern crate elrond_wasm;
 
 pub use crate::events::*;
 pub use crate::node_activation::*;
+pub use crate::ownership::*;
 pub use crate::reset_checkpoints::*;
+pub use crate::reward_history::*;
 pub use crate::rewards::*;
 pub use crate::settings::*;
+pub use crate::time_weighted_rewards::*;
 pub use crate::user_stake::*;
 pub use crate::user_unstake::*;
 
@@ -48,6 +54,7 @@ pub use elrond_wasm_module_pause_wasm as elrond_wasm_module_pause;
 
 pub use elrond_wasm_module_pause::*;
 pub use node_storage::node_config::*;
+pub use node_storage::operators::*;
 pub use node_storage::types::*;
 pub use user_fund_storage::fund_transf_module::*;
 pub use user_fund_storage::fund_view_module::*;
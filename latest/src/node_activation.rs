@@ -2,6 +2,8 @@ use super::node_storage::types::*;
 use crate::auction_proxy::Auction;
 
 use super::node_storage::node_config::*;
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
 use super::user_fund_storage::user_data::*;
 use crate::events::*;
 use crate::reset_checkpoints::*;
@@ -25,6 +27,9 @@ pub trait ContractStakeModule {
     #[module(NodeConfigModuleImpl)]
     fn node_config(&self) -> NodeConfigModuleImpl<T, BigInt, BigUint>;
 
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     #[module(RewardsModuleImpl)]
     fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
 
@@ -34,14 +39,17 @@ pub trait ContractStakeModule {
     #[module(ResetCheckpointsModuleImpl)]
     fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
 
-    /// Owner activates specific nodes.
+    /// Owner or operator activates specific nodes.
     #[endpoint(stakeNodes)]
     fn stake_nodes(
         &self,
         amount_to_stake: BigUint,
         #[var_args] bls_keys: VarArgs<BLSKey>,
     ) -> SCResult<()> {
-        only_owner!(self, "only owner allowed to stake nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator allowed to stake nodes"
+        );
 
         require!(
             !self.settings().is_bootstrap_mode(),
@@ -60,10 +68,12 @@ pub trait ContractStakeModule {
 
         sc_try!(self.user_stake().validate_owner_stake_share());
 
+        let bl_nonce = self.get_block_nonce();
+        let node_stakes = self.split_evenly(&amount_to_stake, bls_keys.len());
         let mut node_ids = Vec::<usize>::with_capacity(bls_keys.len());
         let mut bls_keys_signatures = Vec::<Vec<u8>>::with_capacity(2 * bls_keys.len());
 
-        for bls_key in bls_keys.iter() {
+        for (bls_key, node_stake) in bls_keys.iter().zip(node_stakes.iter()) {
             let node_id = self.node_config().get_node_id(&bls_key);
             require!(node_id != 0, "unknown node provided");
 
@@ -77,12 +87,30 @@ pub trait ContractStakeModule {
             bls_keys_signatures.push(self.node_config().get_node_signature(node_id).to_vec());
 
             self.node_config()
-                .set_node_state(node_id, NodeState::PendingActivation);
+                .set_node_state(node_id, NodeState::PendingActivation { since: bl_nonce });
+            self.node_config().set_node_stake(node_id, node_stake);
         }
 
         self.perform_stake_nodes(node_ids, bls_keys_signatures, amount_to_stake)
     }
 
+    /// Splits an amount (e.g. stake, fine) evenly between the nodes.
+    /// The remainder of the division, if any, is attributed to the first node.
+    fn split_evenly(&self, amount: &BigUint, num_nodes: usize) -> Vec<BigUint> {
+        if num_nodes == 0 {
+            return Vec::new();
+        }
+
+        let amount_per_node = amount.clone() / BigUint::from(num_nodes);
+        let remainder = amount.clone() % BigUint::from(num_nodes);
+        let mut node_amounts = Vec::with_capacity(num_nodes);
+        node_amounts.push(amount_per_node.clone() + remainder);
+        for _ in 1..num_nodes {
+            node_amounts.push(amount_per_node.clone());
+        }
+        node_amounts
+    }
+
     fn perform_stake_nodes(
         &self,
         node_ids: Vec<usize>,
@@ -90,6 +118,8 @@ pub trait ContractStakeModule {
         amount_to_stake: BigUint,
     ) -> SCResult<()> {
         let num_nodes = node_ids.len();
+        self.rewards().add_sent_to_auction(&amount_to_stake);
+
         // send all stake to auction contract
         let auction_contract_addr = self.settings().get_auction_contract_address();
         let auction_contract = contract_proxy!(self, &auction_contract_addr, Auction);
@@ -104,17 +134,30 @@ pub trait ContractStakeModule {
     }
 
     /// Only finalize activation if we got confirmation from the auction contract.
+    /// If the call failed, the stake is sent back before the callback,
+    /// so it is booked even if the callback cannot be processed.
     /// #[callback] can only be declared in lib.rs for the moment.
     fn auction_stake_callback(
         &self,
         node_ids: Vec<usize>, // #[callback_arg]
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) -> SCResult<()> {
+        self.rewards().book_funds_returned_from_auction();
+
+        sc_try!(self.require_nodes_pending(&node_ids, |node_state| matches!(
+            node_state,
+            NodeState::PendingActivation { .. }
+        )));
+
         match call_result {
             AsyncCallResult::Ok(node_status_args) => {
                 let (node_ids_ok, node_ids_fail) = self
                     .node_config()
                     .split_node_ids_by_err(node_ids, node_status_args);
+                for &(node_id, _) in node_ids_fail.iter() {
+                    self.rewards()
+                        .add_unused_in_auction(&self.node_config().get_node_stake(node_id));
+                }
                 sc_try!(self.auction_stake_callback_ok(node_ids_ok));
                 sc_try!(self.auction_stake_callback_fail(
                     node_ids_fail,
@@ -123,7 +166,11 @@ pub trait ContractStakeModule {
                 Ok(())
             }
             AsyncCallResult::Err(error) => {
-                self.auction_stake_callback_fail(node_ids, error.err_msg.as_slice())
+                let node_ids_fail = node_ids
+                    .into_iter()
+                    .map(|node_id| (node_id, error.err_code))
+                    .collect();
+                self.auction_stake_callback_fail(node_ids_fail, error.err_msg.as_slice())
             }
         }
     }
@@ -134,43 +181,190 @@ pub trait ContractStakeModule {
         }
 
         // set nodes to Active
+        let mut node_states = Vec::with_capacity(node_ids.len());
         for &node_id in node_ids.iter() {
-            self.node_config()
-                .set_node_state(node_id, NodeState::Active);
+            node_states.push(self.change_node_state(node_id, NodeState::Active));
         }
 
-        // log event (no data)
-        // TODO: log BLS keys of nodes in data
-        self.events().stake_node_ok_event(());
+        // log event, with the BLS keys of the nodes
+        self.events().stake_node_ok_event(node_states.as_slice());
 
         Ok(())
     }
 
-    fn auction_stake_callback_fail(&self, node_ids: Vec<usize>, err_msg: &[u8]) -> SCResult<()> {
+    fn auction_stake_callback_fail(
+        &self,
+        node_ids: Vec<(usize, i32)>,
+        err_msg: &[u8],
+    ) -> SCResult<()> {
         if node_ids.is_empty() {
             return Ok(());
         }
 
-        // set nodes to Inactive
-        for &node_id in node_ids.iter() {
-            self.node_config()
-                .set_node_state(node_id, NodeState::Inactive);
+        // set nodes to Inactive + reset stake since it was not accepted
+        let mut node_errors = Vec::with_capacity(node_ids.len());
+        for &(node_id, err_code) in node_ids.iter() {
+            let (bls_key, node_state) = self.change_node_state(node_id, NodeState::Inactive);
+            self.node_config().clear_node_stake(node_id);
+            node_errors.push((bls_key, node_state, err_code));
+        }
+
+        // log failure event, with the BLS keys of the nodes and their error codes
+        self.events().stake_node_fail_event(&(err_msg, node_errors));
+
+        Ok(())
+    }
+
+    /// Sets the new state of a node.
+    /// Yields the node BLS key together with the new state, for logging.
+    fn change_node_state(&self, node_id: usize, node_state: NodeState) -> (BLSKey, NodeState) {
+        self.node_config().set_node_state(node_id, node_state);
+        (self.node_config().get_node_id_to_bls(node_id), node_state)
+    }
+
+    // TOP UP
+
+    /// Owner sends additional stake to the auction SC for specific active nodes.
+    /// Each of the nodes receives `amount_per_node`.
+    #[endpoint(topUpNodes)]
+    fn top_up_nodes(
+        &self,
+        amount_per_node: BigUint,
+        #[var_args] bls_keys: VarArgs<BLSKey>,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to top up nodes");
+
+        require!(
+            !self.settings().is_bootstrap_mode(),
+            "cannot top up nodes in bootstrap mode"
+        );
+
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "node operations are temporarily paused as checkpoint is reset"
+        );
+
+        require!(!bls_keys.is_empty(), "no BLS keys provided");
+        require!(amount_per_node > 0, "top up amount must be positive");
+
+        let amount_to_top_up = amount_per_node.clone() * BigUint::from(bls_keys.len());
+        require!(
+            self.rewards().total_unprotected() >= amount_to_top_up,
+            "not enough funds in contract to top up nodes"
+        );
+
+        sc_try!(self.user_stake().validate_owner_stake_share());
+
+        for bls_key in bls_keys.iter() {
+            let node_id = self.node_config().get_node_id(bls_key);
+            require!(node_id != 0, "unknown node provided");
+            require!(
+                self.node_config().get_node_state(node_id) == NodeState::Active,
+                "node not active"
+            );
         }
+        let bls_keys = bls_keys.into_vec();
+
+        self.rewards().add_sent_to_auction(&amount_to_top_up);
 
-        // log failure event (no data)
-        self.events().stake_node_fail_event(err_msg);
+        // send top up to auction contract
+        let auction_contract_addr = self.settings().get_auction_contract_address();
+        let auction_contract = contract_proxy!(self, &auction_contract_addr, Auction);
+        auction_contract.topUp(
+            bls_keys.clone(),        // callback arg
+            amount_per_node.clone(), // callback arg
+            bls_keys.into(),
+            &amount_to_top_up,
+        );
 
         Ok(())
     }
 
+    /// Top-ups only get added to the node stake if confirmed by the auction contract.
+    /// Top-ups that failed for some of the nodes stay in the auction SC as unused funds, until claimed.
+    /// Nodes are passed by BLS key, since node ids can change if removed nodes are purged in the meantime.
+    /// #[callback] can only be declared in lib.rs for the moment.
+    fn auction_top_up_callback(
+        &self,
+        node_bls_keys: Vec<BLSKey>, // #[callback_arg]
+        amount_per_node: BigUint,   // #[callback_arg]
+        call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
+    ) {
+        let node_ids = self.node_config().get_registered_node_ids(&node_bls_keys);
+
+        match call_result {
+            AsyncCallResult::Ok(node_status_args) => {
+                let (node_ids_ok, node_ids_fail) = self
+                    .node_config()
+                    .split_node_ids_by_err(node_ids, node_status_args);
+                let num_nodes_fail = node_bls_keys.len() - node_ids_ok.len();
+                self.rewards().add_unused_in_auction(
+                    &(amount_per_node.clone() * BigUint::from(num_nodes_fail)),
+                );
+                self.auction_top_up_callback_ok(node_ids_ok, &amount_per_node);
+                self.auction_top_up_callback_fail(
+                    node_ids_fail,
+                    &b"top up failed for some nodes"[..],
+                );
+            }
+            AsyncCallResult::Err(error) => {
+                // the whole top up is sent back if the call failed
+                self.rewards().book_funds_returned_from_auction();
+
+                let node_ids_fail = node_ids
+                    .into_iter()
+                    .map(|node_id| (node_id, error.err_code))
+                    .collect();
+                self.auction_top_up_callback_fail(node_ids_fail, error.err_msg.as_slice());
+            }
+        }
+    }
+
+    fn auction_top_up_callback_ok(&self, node_ids: Vec<usize>, amount_per_node: &BigUint) {
+        if node_ids.is_empty() {
+            return;
+        }
+
+        // add top up to the node stake
+        let mut node_stakes = Vec::with_capacity(node_ids.len());
+        for &node_id in node_ids.iter() {
+            let node_stake = self.node_config().get_node_stake(node_id) + amount_per_node.clone();
+            self.node_config().set_node_stake(node_id, &node_stake);
+            node_stakes.push((self.node_config().get_node_id_to_bls(node_id), node_stake));
+        }
+
+        // log event, with the BLS keys of the nodes and their new stake
+        self.events()
+            .top_up_node_ok_event(amount_per_node, node_stakes.as_slice());
+    }
+
+    fn auction_top_up_callback_fail(&self, node_ids: Vec<(usize, i32)>, err_msg: &[u8]) {
+        if node_ids.is_empty() {
+            return;
+        }
+
+        // node stake stays unchanged
+        let mut node_errors = Vec::with_capacity(node_ids.len());
+        for &(node_id, err_code) in node_ids.iter() {
+            node_errors.push((self.node_config().get_node_id_to_bls(node_id), err_code));
+        }
+
+        // log failure event, with the BLS keys of the nodes and their error codes
+        self.events()
+            .top_up_node_fail_event(&(err_msg, node_errors));
+    }
+
     // UNSTAKE
 
     /// Unstakes from the auction smart contract.
     /// The nodes will stop receiving rewards, but stake cannot be yet reclaimed.
-    /// This operation is performed by the owner.
+    /// This operation is performed by the owner or an operator.
     #[endpoint(unStakeNodes)]
     fn unstake_nodes(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
-        only_owner!(self, "only owner allowed to unstake nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator allowed to unstake nodes"
+        );
 
         require!(
             !self.reset_checkpoints().is_global_op_in_progress(),
@@ -187,16 +381,20 @@ pub trait ContractStakeModule {
         self.perform_unstake_nodes(node_ids, bls_keys.into_vec())
     }
 
+    /// Jailed nodes can also be unstaked, instead of paying the fine.
+    /// If unstaking fails, they revert to Active like the other nodes, until marked jailed again.
     fn perform_unstake_nodes(&self, node_ids: Vec<usize>, bls_keys: Vec<BLSKey>) -> SCResult<()> {
         // convert node state to PendingDeactivation
+        let bl_nonce = self.get_block_nonce();
         for &node_id in node_ids.iter() {
+            let node_state = self.node_config().get_node_state(node_id);
             require!(
-                self.node_config().get_node_state(node_id) == NodeState::Active,
+                node_state == NodeState::Active || node_state == NodeState::Jailed,
                 "node not active"
             );
 
             self.node_config()
-                .set_node_state(node_id, NodeState::PendingDeactivation);
+                .set_node_state(node_id, NodeState::PendingDeactivation { since: bl_nonce });
         }
 
         // send unstake command to Auction SC
@@ -214,6 +412,11 @@ pub trait ContractStakeModule {
         node_ids: Vec<usize>, // #[callback_arg]
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) -> SCResult<()> {
+        sc_try!(self.require_nodes_pending(&node_ids, |node_state| matches!(
+            node_state,
+            NodeState::PendingDeactivation { .. }
+        )));
+
         match call_result {
             AsyncCallResult::Ok(node_status_args) => {
                 let (node_ids_ok, node_ids_fail) = self
@@ -227,7 +430,11 @@ pub trait ContractStakeModule {
                 Ok(())
             }
             AsyncCallResult::Err(error) => {
-                self.auction_unstake_callback_fail(node_ids, error.err_msg.as_slice())
+                let node_ids_fail = node_ids
+                    .into_iter()
+                    .map(|node_id| (node_id, error.err_code))
+                    .collect();
+                self.auction_unstake_callback_fail(node_ids_fail, error.err_msg.as_slice())
             }
         }
     }
@@ -239,31 +446,38 @@ pub trait ContractStakeModule {
 
         // set nodes to UnBondPeriod + save current block nonce
         let bl_nonce = self.get_block_nonce();
+        let mut node_states = Vec::with_capacity(node_ids.len());
         for &node_id in node_ids.iter() {
-            self.node_config()
-                .set_node_state(node_id, NodeState::UnBondPeriod { started: bl_nonce });
+            node_states.push(
+                self.change_node_state(node_id, NodeState::UnBondPeriod { started: bl_nonce }),
+            );
         }
 
-        // log event (no data)
-        // TODO: log BLS keys of nodes in data
-        self.events().unstake_node_ok_event(());
+        // log event, with the BLS keys of the nodes
+        self.events().unstake_node_ok_event(node_states.as_slice());
 
         Ok(())
     }
 
-    fn auction_unstake_callback_fail(&self, node_ids: Vec<usize>, err_msg: &[u8]) -> SCResult<()> {
+    fn auction_unstake_callback_fail(
+        &self,
+        node_ids: Vec<(usize, i32)>,
+        err_msg: &[u8],
+    ) -> SCResult<()> {
         if node_ids.is_empty() {
             return Ok(());
         }
 
         // revert nodes to Active
-        for &node_id in node_ids.iter() {
-            self.node_config()
-                .set_node_state(node_id, NodeState::Active);
+        let mut node_errors = Vec::with_capacity(node_ids.len());
+        for &(node_id, err_code) in node_ids.iter() {
+            let (bls_key, node_state) = self.change_node_state(node_id, NodeState::Active);
+            node_errors.push((bls_key, node_state, err_code));
         }
 
-        // log failure event (no data)
-        self.events().unstake_node_fail_event(err_msg);
+        // log failure event, with the BLS keys of the nodes and their error codes
+        self.events()
+            .unstake_node_fail_event(&(err_msg, node_errors));
 
         Ok(())
     }
@@ -272,7 +486,10 @@ pub trait ContractStakeModule {
     /// Calls unbond for all provided nodes. Will fail if node cannot be unbonded.
     #[endpoint(unBondNodes)]
     fn unbond_specific_nodes(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
-        only_owner!(self, "only owner allowed to unbond nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator allowed to unbond nodes"
+        );
 
         require!(
             !self.reset_checkpoints().is_global_op_in_progress(),
@@ -296,20 +513,25 @@ pub trait ContractStakeModule {
     }
 
     /// Calls unbond for all nodes that are in the unbond period and are due.
+    /// If `max_count` is provided, at most that many nodes are unbonded, starting with the highest node ids.
     /// Nothing happens if no nodes can be unbonded.
     #[endpoint(unBondAllPossibleNodes)]
-    fn unbond_all_possible_nodes(&self) -> SCResult<()> {
-        only_owner!(self, "only owner allowed to unbond nodes");
+    fn unbond_all_possible_nodes(&self, #[var_args] max_count: OptionalArg<usize>) -> SCResult<()> {
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator allowed to unbond nodes"
+        );
 
         require!(
             !self.reset_checkpoints().is_global_op_in_progress(),
             "node operations are temporarily paused as checkpoint is reset"
         );
 
+        let max_count = max_count.into_option().unwrap_or(usize::MAX);
         let mut node_id = self.node_config().get_num_nodes();
         let mut node_ids = Vec::<usize>::new();
         let mut bls_keys = Vec::<BLSKey>::new();
-        while node_id >= 1 {
+        while node_id >= 1 && node_ids.len() < max_count {
             if self.prepare_node_for_unbond_if_possible(node_id) {
                 node_ids.push(node_id);
                 bls_keys.push(self.node_config().get_node_id_to_bls(node_id));
@@ -331,6 +553,7 @@ pub trait ContractStakeModule {
                 node_id,
                 NodeState::PendingUnBond {
                     unbond_started: started,
+                    since: self.get_block_nonce(),
                 },
             );
             return true;
@@ -349,12 +572,22 @@ pub trait ContractStakeModule {
     }
 
     /// Only finalize deactivation if we got confirmation from the auction contract.
+    /// The stake of the unbonded nodes is sent back before the callback,
+    /// so it is booked even if the callback cannot be processed.
     /// #[callback] can only be declared in lib.rs for the moment.
     fn auction_unbond_callback(
         &self,
         node_ids: Vec<usize>, // #[callback_arg]
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) -> SCResult<()> {
+        let returned = self.rewards().book_funds_returned_from_auction();
+        self.events().unbond_funds_returned_event(&returned);
+
+        sc_try!(self.require_nodes_pending(&node_ids, |node_state| matches!(
+            node_state,
+            NodeState::PendingUnBond { .. }
+        )));
+
         match call_result {
             AsyncCallResult::Ok(node_status_args) => {
                 let (node_ids_ok, node_ids_fail) = self
@@ -368,7 +601,11 @@ pub trait ContractStakeModule {
                 Ok(())
             }
             AsyncCallResult::Err(error) => {
-                self.auction_unbond_callback_fail(node_ids, error.err_msg.as_slice())
+                let node_ids_fail = node_ids
+                    .into_iter()
+                    .map(|node_id| (node_id, error.err_code))
+                    .collect();
+                self.auction_unbond_callback_fail(node_ids_fail, error.err_msg.as_slice())
             }
         }
     }
@@ -378,42 +615,49 @@ pub trait ContractStakeModule {
             return Ok(());
         }
 
-        // set nodes to Inactive + reset unstake nonce since it is no longer needed
+        // set nodes to Inactive + reset unstake nonce and stake since they are no longer needed
+        let mut node_states = Vec::with_capacity(node_ids.len());
         for &node_id in node_ids.iter() {
-            self.node_config()
-                .set_node_state(node_id, NodeState::Inactive);
+            node_states.push(self.change_node_state(node_id, NodeState::Inactive));
+            self.node_config().clear_node_stake(node_id);
         }
 
-        // log event (no data)
-        // TODO: log BLS keys of nodes in data
-        self.events().unbond_node_ok_event(());
+        // log event, with the BLS keys of the nodes
+        self.events().unbond_node_ok_event(node_states.as_slice());
 
         Ok(())
     }
 
-    fn auction_unbond_callback_fail(&self, node_ids: Vec<usize>, err_msg: &[u8]) -> SCResult<()> {
+    fn auction_unbond_callback_fail(
+        &self,
+        node_ids: Vec<(usize, i32)>,
+        err_msg: &[u8],
+    ) -> SCResult<()> {
         if node_ids.is_empty() {
             return Ok(());
         }
 
         // revert nodes to UnBondPeriod
-        for &node_id in node_ids.iter() {
-            if let NodeState::PendingUnBond { unbond_started } =
+        let mut node_errors = Vec::with_capacity(node_ids.len());
+        for &(node_id, err_code) in node_ids.iter() {
+            if let NodeState::PendingUnBond { unbond_started, .. } =
                 self.node_config().get_node_state(node_id)
             {
-                self.node_config().set_node_state(
+                let (bls_key, node_state) = self.change_node_state(
                     node_id,
                     NodeState::UnBondPeriod {
                         started: unbond_started,
                     },
                 );
+                node_errors.push((bls_key, node_state, err_code));
             } else {
                 return sc_error!("node not pending unbond");
             }
         }
 
-        // log failure event (no data)
-        self.events().unbond_node_fail_event(err_msg);
+        // log failure event, with the BLS keys of the nodes and their error codes
+        self.events()
+            .unbond_node_fail_event(&(err_msg, node_errors));
 
         Ok(())
     }
@@ -421,7 +665,7 @@ pub trait ContractStakeModule {
     /// Claims from auction SC funds that were sent but are not required to run the nodes.
     #[endpoint(claimUnusedFunds)]
     fn claim_unused_funds(&self) -> SCResult<()> {
-        only_owner!(self, "only owner can claim inactive stake from auction");
+        only_delegation_owner!(self, "only owner can claim inactive stake from auction");
 
         require!(
             !self.reset_checkpoints().is_global_op_in_progress(),
@@ -436,6 +680,58 @@ pub trait ContractStakeModule {
         Ok(())
     }
 
+    /// Unused funds are sent back before the callback.
+    /// They are the stake in the auction SC that is not attributed to any node,
+    /// i.e. stake and top-ups that the auction SC accepted but that failed for some of the nodes.
+    /// #[callback] can only be declared in lib.rs for the moment.
+    fn auction_claim_callback(&self, call_result: AsyncCallResult<()>) {
+        match call_result {
+            AsyncCallResult::Ok(()) => {
+                let returned = self.rewards().book_funds_returned_from_auction();
+                self.rewards().set_total_unused_in_auction(&BigUint::zero());
+                self.events().claim_unused_funds_event(&returned);
+            }
+            AsyncCallResult::Err(error) => {
+                self.events()
+                    .callback_error_event(&(error.err_msg.as_slice(), Vec::new()));
+            }
+        }
+    }
+
+    // JAIL
+
+    /// Owner marks nodes that were jailed by the protocol.
+    /// Jailed nodes can also be detected with `reconcileNodeStates`.
+    #[endpoint(markNodesJailed)]
+    fn mark_nodes_jailed(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to mark nodes jailed");
+
+        require!(!bls_keys.is_empty(), "no BLS keys provided");
+
+        let mut node_ids = Vec::<usize>::with_capacity(bls_keys.len());
+        for bls_key in bls_keys.iter() {
+            let node_id = self.node_config().get_node_id(bls_key);
+            require!(node_id != 0, "unknown node provided");
+            require!(
+                self.node_config().get_node_state(node_id) == NodeState::Active,
+                "node must be active"
+            );
+            node_ids.push(node_id);
+        }
+
+        let mut node_states = Vec::with_capacity(node_ids.len());
+        for &node_id in node_ids.iter() {
+            node_states.push(self.change_node_state(node_id, NodeState::Jailed));
+        }
+
+        // log event, with the BLS keys of the nodes
+        self.events().jail_nodes_event(node_states.as_slice());
+
+        Ok(())
+    }
+
+    /// Owner or operator pays the fine to unjail nodes.
+    /// Nodes not yet known to be jailed can also be unjailed, as long as they are active.
     #[payable]
     #[endpoint(unJailNodes)]
     fn unjail_nodes(
@@ -443,23 +739,364 @@ pub trait ContractStakeModule {
         #[var_args] bls_keys: VarArgs<BLSKey>,
         #[payment] fine_payment: &BigUint,
     ) -> SCResult<()> {
-        only_owner!(self, "only owner allowed to unjail nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator allowed to unjail nodes"
+        );
+
+        require!(*fine_payment > 0, "fine payment required");
 
-        // validation only
         for bls_key in bls_keys.iter() {
             let node_id = self.node_config().get_node_id(&bls_key);
             require!(node_id != 0, "unknown node provided");
+            let node_state = self.node_config().get_node_state(node_id);
             require!(
-                self.node_config().get_node_state(node_id) == NodeState::Active,
-                "node must be active"
+                node_state == NodeState::Jailed || node_state == NodeState::Active,
+                "node must be jailed or active"
             );
         }
+        let bls_keys = bls_keys.into_vec();
 
         // send unJail command to Auction SC
         let auction_contract_addr = self.settings().get_auction_contract_address();
         let auction_contract = contract_proxy!(self, &auction_contract_addr, Auction);
-        auction_contract.unJail(bls_keys, fine_payment);
+        auction_contract.unJail(
+            bls_keys.clone(),
+            fine_payment.clone(),
+            self.get_caller(),
+            bls_keys.into(),
+            fine_payment,
+        );
+
+        Ok(())
+    }
+
+    /// Jailed nodes only become active again if the auction contract confirms the unjail.
+    /// If unjailing fails, the fine is sent back to whoever paid it.
+    /// Nodes are passed by BLS key, same as for top-ups.
+    /// #[callback] can only be declared in lib.rs for the moment.
+    fn auction_unjail_callback(
+        &self,
+        node_bls_keys: Vec<BLSKey>, // #[callback_arg]
+        fine_payment: BigUint,      // #[callback_arg]
+        fine_payer: Address,        // #[callback_arg]
+        call_result: AsyncCallResult<()>,
+    ) {
+        let node_ids = self.node_config().get_registered_node_ids(&node_bls_keys);
+
+        match call_result {
+            AsyncCallResult::Ok(()) => {
+                let node_fines = self.split_evenly(&fine_payment, node_ids.len());
+                let mut node_states = Vec::with_capacity(node_ids.len());
+                for (&node_id, node_fine) in node_ids.iter().zip(node_fines.iter()) {
+                    let node_fines_paid =
+                        self.node_config().get_node_fines_paid(node_id) + node_fine.clone();
+                    self.node_config()
+                        .set_node_fines_paid(node_id, &node_fines_paid);
+
+                    if self.node_config().get_node_state(node_id) == NodeState::Jailed {
+                        node_states.push(self.change_node_state(node_id, NodeState::Active));
+                    } else {
+                        node_states.push((
+                            self.node_config().get_node_id_to_bls(node_id),
+                            self.node_config().get_node_state(node_id),
+                        ));
+                    }
+                }
+
+                let total_fines_paid =
+                    self.node_config().get_total_fines_paid() + fine_payment.clone();
+                self.node_config().set_total_fines_paid(&total_fines_paid);
+
+                // log event, with the fine and the BLS keys of the nodes
+                self.events()
+                    .unjail_node_ok_event(&fine_payment, node_states.as_slice());
+            }
+            AsyncCallResult::Err(error) => {
+                // the fine came back with the failed call
+                self.send_tx(&fine_payer, &fine_payment, "unjail fine refund");
+
+                self.events().unjail_node_fail_event(&(
+                    error.err_msg.as_slice(),
+                    error.err_code,
+                    node_bls_keys,
+                ));
+            }
+        }
+    }
+
+    // RECONCILIATION
+
+    /// Queries the auction SC for the status of the given nodes, or of all registered nodes if none are given.
+    /// Nodes whose state does not match the auction SC are logged
+    /// and, if `fix_mismatches` is set, moved to the state reported by the auction SC.
+    /// The recorded stake of nodes moved to Inactive becomes unused funds in the auction SC, until claimed.
+    /// Removed nodes and nodes waiting for an auction SC callback are never changed.
+    #[endpoint(reconcileNodeStates)]
+    fn reconcile_node_states(
+        &self,
+        fix_mismatches: bool,
+        #[var_args] bls_keys: VarArgs<BLSKey>,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to reconcile node states");
+
+        let mut bls_keys = bls_keys.into_vec();
+        if bls_keys.is_empty() {
+            let num_nodes = self.node_config().get_num_nodes();
+            for node_id in 1..num_nodes + 1 {
+                if self.node_config().get_node_state(node_id) != NodeState::Removed {
+                    bls_keys.push(self.node_config().get_node_id_to_bls(node_id));
+                }
+            }
+        } else {
+            for bls_key in bls_keys.iter() {
+                require!(
+                    self.node_config().get_node_id(bls_key) != 0,
+                    "unknown node provided"
+                );
+            }
+        }
+
+        require!(!bls_keys.is_empty(), "no nodes to reconcile");
+
+        let auction_contract_addr = self.settings().get_auction_contract_address();
+        let auction_contract = contract_proxy!(self, &auction_contract_addr, Auction);
+        auction_contract.getBlsKeysStatus(fix_mismatches, bls_keys.into());
+
+        Ok(())
+    }
+
+    /// #[callback] can only be declared in lib.rs for the moment.
+    fn auction_bls_keys_status_callback(
+        &self,
+        fix_mismatches: bool, // #[callback_arg]
+        call_result: AsyncCallResult<VarArgs<BLSAuctionStatusMultiArg>>,
+    ) {
+        match call_result {
+            AsyncCallResult::Ok(bls_status_args) => {
+                let bl_nonce = self.get_block_nonce();
+                let mut mismatches = Vec::new();
+                for arg in bls_status_args.into_vec().into_iter() {
+                    let (bls_key, auction_status) = arg.into_tuple();
+                    let node_id = self.node_config().get_node_id(&bls_key);
+                    if node_id == 0 {
+                        continue;
+                    }
+
+                    let node_state = self.node_config().get_node_state(node_id);
+                    if let Some(auction_state) =
+                        auction_node_state(node_state, auction_status.as_slice(), bl_nonce)
+                    {
+                        if fix_mismatches {
+                            if auction_state == NodeState::Inactive {
+                                self.rewards().add_unused_in_auction(
+                                    &self.node_config().get_node_stake(node_id),
+                                );
+                                self.node_config().clear_node_stake(node_id);
+                            }
+                            self.node_config().set_node_state(node_id, auction_state);
+                            self.node_config().clear_node_callback_error(node_id);
+                        }
+                        mismatches.push((bls_key, node_state, auction_state));
+                    }
+                }
+
+                if !mismatches.is_empty() {
+                    self.events()
+                        .node_state_mismatch_event(&(fix_mismatches, mismatches));
+                }
+            }
+            AsyncCallResult::Err(error) => {
+                self.events()
+                    .callback_error_event(&(error.err_msg.as_slice(), Vec::new()));
+            }
+        }
+    }
+
+    /// Queries the auction SC for the total stake of the contract.
+    /// The result is logged together with the total stake of the nodes, as recorded by the contract.
+    #[endpoint(reconcileTotalStake)]
+    fn reconcile_total_stake(&self) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to reconcile total stake");
+
+        let auction_contract_addr = self.settings().get_auction_contract_address();
+        let auction_contract = contract_proxy!(self, &auction_contract_addr, Auction);
+        auction_contract.getTotalStaked();
+
+        Ok(())
+    }
+
+    /// #[callback] can only be declared in lib.rs for the moment.
+    fn auction_total_staked_callback(&self, call_result: AsyncCallResult<BigUint>) {
+        match call_result {
+            AsyncCallResult::Ok(auction_total_staked) => {
+                self.events().auction_total_staked_event(
+                    &auction_total_staked,
+                    &self.node_config().get_total_node_stake(),
+                );
+            }
+            AsyncCallResult::Err(error) => {
+                self.events()
+                    .callback_error_event(&(error.err_msg.as_slice(), Vec::new()));
+            }
+        }
+    }
+
+    // CALLBACK ERRORS
+
+    /// Callbacks are only valid for nodes that are still waiting for them.
+    /// Checking all nodes before changing anything ensures that
+    /// a callback that cannot be processed leaves all node states untouched.
+    fn require_nodes_pending<F: Fn(NodeState) -> bool>(
+        &self,
+        node_ids: &[usize],
+        is_expected_pending_state: F,
+    ) -> SCResult<()> {
+        for &node_id in node_ids.iter() {
+            require!(
+                is_expected_pending_state(self.node_config().get_node_state(node_id)),
+                "node not pending callback"
+            );
+        }
+        Ok(())
+    }
+
+    /// Auction SC callbacks cannot be retried, so they should not fail.
+    /// If processing the callback does fail, the error is saved for each of the nodes involved
+    /// and the nodes remain in their pending state until the owner resolves them.
+    fn save_callback_result(&self, node_ids: &[usize], result: SCResult<()>) {
+        if let SCResult::Err(error) = result {
+            let mut bls_keys = Vec::<BLSKey>::with_capacity(node_ids.len());
+            for &node_id in node_ids.iter() {
+                self.node_config()
+                    .set_node_callback_error(node_id, error.as_bytes());
+                bls_keys.push(self.node_config().get_node_id_to_bls(node_id));
+            }
+
+            self.events()
+                .callback_error_event(&(error.as_bytes(), bls_keys));
+        }
+    }
+
+    /// Clears the callback errors of the given nodes.
+    /// Nodes that are still pending are reverted to the state they were in before the operation,
+    /// so that the operation can be attempted again.
+    #[endpoint(resolveCallbackErrors)]
+    fn resolve_callback_errors(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to resolve callback errors");
+
+        require!(!bls_keys.is_empty(), "no BLS keys provided");
+
+        for bls_key in bls_keys.iter() {
+            let node_id = self.node_config().get_node_id(bls_key);
+            require!(node_id != 0, "unknown node provided");
+            require!(
+                !self.node_config().is_empty_node_callback_error(node_id),
+                "node has no callback error"
+            );
+
+            let node_state = self.node_config().get_node_state(node_id);
+            if let Some(state_before_pending) = node_state.state_before_pending() {
+                self.revert_pending_node(node_id, state_before_pending);
+            }
+            self.node_config().clear_node_callback_error(node_id);
+        }
+
+        Ok(())
+    }
+
+    /// Nodes can get stuck in a pending state if the auction SC callback never arrives.
+    /// After the configured number of blocks, the owner can revert them
+    /// to the state they were in before the operation.
+    /// Not possible until that number of blocks is configured.
+    /// The owner should first check the auction SC to confirm that the operation did not go through.
+    #[endpoint(recoverPendingNodes)]
+    fn recover_pending_nodes(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner allowed to recover pending nodes");
+
+        require!(!bls_keys.is_empty(), "no BLS keys provided");
+
+        let bl_nonce = self.get_block_nonce();
+        // recovering right away would race callbacks that are still in flight
+        let n_blocks_before_recovery = self.settings().get_n_blocks_before_pending_recovery();
+        require!(
+            n_blocks_before_recovery > 0,
+            "pending recovery delay not configured"
+        );
+        let mut node_states = Vec::with_capacity(bls_keys.len());
+        for bls_key in bls_keys.iter() {
+            let node_id = self.node_config().get_node_id(bls_key);
+            require!(node_id != 0, "unknown node provided");
+
+            let node_state = self.node_config().get_node_state(node_id);
+            let (since, state_before_pending) = match (
+                node_state.pending_since(),
+                node_state.state_before_pending(),
+            ) {
+                (Some(since), Some(state_before_pending)) => (since, state_before_pending),
+                _ => return sc_error!("node not pending"),
+            };
+            require!(
+                bl_nonce >= since + n_blocks_before_recovery,
+                "node has not been pending for long enough"
+            );
+
+            node_states.push(self.revert_pending_node(node_id, state_before_pending));
+            self.node_config().clear_node_callback_error(node_id);
+        }
+
+        // log event, with the BLS keys of the nodes
+        self.events()
+            .recover_pending_nodes_event(node_states.as_slice());
 
         Ok(())
     }
+
+    /// Nodes reverted to Inactive are no longer staked.
+    /// Their stake is booked as returned from the auction SC once it is back in the contract balance,
+    /// measured the same way as in the callbacks, so a refund that was already booked is not booked twice.
+    fn revert_pending_node(
+        &self,
+        node_id: usize,
+        state_before_pending: NodeState,
+    ) -> (BLSKey, NodeState) {
+        if state_before_pending == NodeState::Inactive {
+            self.node_config().clear_node_stake(node_id);
+            self.rewards().book_funds_returned_from_auction();
+        }
+        self.change_node_state(node_id, state_before_pending)
+    }
+}
+
+/// The state a node should be in, given its status in the auction SC.
+/// Yields None if the node state matches the auction SC, or if the auction status is not recognized.
+/// Removed nodes are never brought back, and pending nodes are left to their callbacks or to recovery.
+fn auction_node_state(
+    node_state: NodeState,
+    auction_status: &[u8],
+    bl_nonce: u64,
+) -> Option<NodeState> {
+    if node_state == NodeState::Removed || node_state.pending_since().is_some() {
+        return None;
+    }
+
+    let auction_state = match auction_status {
+        b"staked" => NodeState::Active,
+        b"jailed" => NodeState::Jailed,
+        b"unStaked" => match node_state {
+            NodeState::UnBondPeriod { started } => NodeState::UnBondPeriod { started },
+            NodeState::PendingUnBond { unbond_started, .. } => NodeState::UnBondPeriod {
+                started: unbond_started,
+            },
+            _ => NodeState::UnBondPeriod { started: bl_nonce },
+        },
+        b"notStaked" => NodeState::Inactive,
+        _ => return None,
+    };
+
+    if node_state == auction_state {
+        None
+    } else {
+        Some(auction_state)
+    }
 }
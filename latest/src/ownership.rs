@@ -0,0 +1,199 @@
+use super::node_storage::operators::*;
+use super::settings::*;
+use super::user_fund_storage::fund_transf_module::*;
+use super::user_fund_storage::user_data::*;
+use crate::events::*;
+use crate::reset_checkpoints::*;
+use crate::rewards::*;
+use crate::time_weighted_rewards::*;
+use crate::user_stake::*;
+use core::num::NonZeroUsize;
+
+imports!();
+
+/// Moves the delegation ownership to a new address, in 2 steps:
+/// the owner proposes the new owner, then the new owner accepts.
+/// Ownership means being user #1, which receives the service fee
+/// and the rewards for the stake that is not delegated,
+/// and being allowed to call the owner-only endpoints.
+/// The old owner keeps its stake and rewards, as a regular delegator with a new user id,
+/// while user #1 starts over for the new owner.
+/// The pause and feature flag endpoints come from external modules and still check the contract owner
+/// known by the protocol, which needs to be changed separately, with the `ChangeOwnerAddress` built-in function.
+#[elrond_wasm_derive::module(OwnershipModuleImpl)]
+pub trait OwnershipModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(EventsModuleImpl)]
+    fn events(&self) -> EventsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(FundTransformationsModuleImpl)]
+    fn fund_transf_module(&self) -> FundTransformationsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(UserDataModuleImpl)]
+    fn user_data(&self) -> UserDataModuleImpl<T, BigInt, BigUint>;
+
+    #[module(RewardsModuleImpl)]
+    fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(ResetCheckpointsModuleImpl)]
+    fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(UserStakeModuleImpl)]
+    fn user_stake(&self) -> UserStakeModuleImpl<T, BigInt, BigUint>;
+
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
+    #[storage_get("pending_owner")]
+    fn get_pending_owner(&self) -> Address;
+
+    #[storage_set("pending_owner")]
+    fn set_pending_owner(&self, pending_owner: &Address);
+
+    #[storage_is_empty("pending_owner")]
+    fn is_empty_pending_owner(&self) -> bool;
+
+    #[storage_clear("pending_owner")]
+    fn clear_pending_owner(&self);
+
+    /// The address proposed as new owner, if a transfer is in progress.
+    #[view(getPendingOwner)]
+    fn get_pending_owner_endpoint(&self) -> OptionalResult<Address> {
+        if self.is_empty_pending_owner() {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(self.get_pending_owner())
+        }
+    }
+
+    /// First step: the owner proposes the new owner.
+    /// A new proposal replaces the previous one.
+    /// The new owner must not already be a delegator, since it will take over user #1.
+    #[endpoint(proposeOwnershipTransfer)]
+    fn propose_ownership_transfer(&self, new_owner: Address) -> SCResult<()> {
+        let owner = self.operators().get_delegation_owner();
+        require!(
+            self.get_caller() == owner,
+            "only owner can transfer ownership"
+        );
+        require!(new_owner != owner, "address is already the owner");
+        require!(
+            self.user_data().get_user_id(&new_owner) == 0,
+            "new owner must not be a delegator"
+        );
+
+        self.set_pending_owner(&new_owner);
+
+        self.events()
+            .ownership_transfer_proposed_event(&owner, &new_owner);
+        Ok(())
+    }
+
+    #[endpoint(cancelOwnershipTransfer)]
+    fn cancel_ownership_transfer(&self) -> SCResult<()> {
+        require!(
+            self.get_caller() == self.operators().get_delegation_owner(),
+            "only owner can cancel ownership transfer"
+        );
+        require!(
+            !self.is_empty_pending_owner(),
+            "no ownership transfer in progress"
+        );
+
+        self.clear_pending_owner();
+        Ok(())
+    }
+
+    /// Second step: the proposed owner accepts.
+    /// The owner rewards are computed up to now and stay with the old owner, everything from now on goes to the new owner.
+    /// The new owner starts with no stake, so it needs to stake enough for the minimum owner stake share
+    /// before nodes can be staked or topped up again.
+    #[endpoint(acceptOwnershipTransfer)]
+    fn accept_ownership_transfer(&self) -> SCResult<()> {
+        require!(
+            !self.is_empty_pending_owner(),
+            "no ownership transfer in progress"
+        );
+        let new_owner = self.get_caller();
+        require!(
+            new_owner == self.get_pending_owner(),
+            "only the proposed owner can accept ownership"
+        );
+        require!(
+            self.user_data().get_user_id(&new_owner) == 0,
+            "new owner must not be a delegator"
+        );
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "ownership transfer is temporarily paused as checkpoint is reset"
+        );
+
+        // settle old owner rewards
+        self.rewards().compute_one_user_reward(OWNER_USER_ID);
+
+        // the old owner becomes a regular delegator, with everything it had
+        let old_owner = self.operators().get_delegation_owner();
+        let old_owner_id = self.user_data().new_user();
+        self.user_data().set_user_id(&old_owner, old_owner_id);
+        self.user_data().set_user_address(old_owner_id, &old_owner);
+        self.fund_transf_module()
+            .move_all_user_funds(OWNER_USER_ID.get(), old_owner_id);
+        if let Some(old_owner_id) = NonZeroUsize::new(old_owner_id) {
+            self.move_owner_user_data(old_owner_id);
+        }
+
+        // user #1 now belongs to the new owner
+        self.user_data()
+            .set_user_id(&new_owner, OWNER_USER_ID.get());
+        self.user_data()
+            .set_user_address(OWNER_USER_ID.get(), &new_owner);
+        self.operators().set_delegation_owner(&new_owner);
+        self.clear_pending_owner();
+
+        self.events()
+            .ownership_transferred_event(&old_owner, &new_owner);
+        Ok(())
+    }
+
+    /// Moves the per-user data of user #1 to the new user id of the old owner,
+    /// after the owner rewards were computed up to now, and resets it for the new owner.
+    /// The time-weighted period stays the same for both, only the stake-blocks go with the stake.
+    fn move_owner_user_data(&self, old_owner_id: NonZeroUsize) {
+        let rewards = self.rewards();
+        rewards.set_user_rew_checkpoint(
+            old_owner_id,
+            &rewards.get_user_rew_checkpoint(OWNER_USER_ID),
+        );
+        rewards
+            .set_user_rew_unclaimed(old_owner_id, &rewards.get_user_rew_unclaimed(OWNER_USER_ID));
+        rewards.set_user_rew_unclaimed(OWNER_USER_ID, &BigUint::zero());
+        rewards.set_user_rew_claimed(old_owner_id, &rewards.get_user_rew_claimed(OWNER_USER_ID));
+        rewards.set_user_rew_claimed(OWNER_USER_ID, &BigUint::zero());
+        rewards.set_user_last_claim_nonce(
+            old_owner_id,
+            rewards.get_user_last_claim_nonce(OWNER_USER_ID),
+        );
+        rewards.set_user_last_claim_nonce(OWNER_USER_ID, 0);
+
+        let user_stake = self.user_stake();
+        user_stake.set_auto_compound(old_owner_id, user_stake.is_auto_compound(OWNER_USER_ID));
+        user_stake.set_auto_compound(OWNER_USER_ID, false);
+
+        let time_weighted_rewards = self.time_weighted_rewards();
+        time_weighted_rewards.set_user_period(
+            old_owner_id,
+            time_weighted_rewards.get_user_period(OWNER_USER_ID),
+        );
+        time_weighted_rewards.set_user_mark_block(
+            old_owner_id,
+            time_weighted_rewards.get_user_mark_block(OWNER_USER_ID),
+        );
+        time_weighted_rewards.set_user_stake_blocks(
+            old_owner_id,
+            &time_weighted_rewards.get_user_stake_blocks(OWNER_USER_ID),
+        );
+        time_weighted_rewards.set_user_stake_blocks(OWNER_USER_ID, &BigUint::zero());
+    }
+}
@@ -43,10 +43,15 @@ impl TopEncode for GlobalOpStatus {
 pub enum GlobalOpCheckpoint<BigUint: BigUintApi> {
     None,
     ModifyTotalDelegationCap(ModifyTotalDelegationCapData<BigUint>),
+    /// Service fee changes no longer need to compute all rewards, they end the reward regime instead.
+    /// Only kept so that operations in progress during an upgrade can finish.
     ChangeServiceFee {
         new_service_fee: BigUint,
         compute_rewards_data: ComputeAllRewardsData<BigUint>,
     },
+    DistributeRewards(DistributeRewardsData<BigUint>),
+    /// Computes the rewards of all users, then gives the rounding remainder to the owner.
+    SweepDust(ComputeAllRewardsData<BigUint>),
 }
 
 impl<BigUint: BigUintApi> GlobalOpCheckpoint<BigUint> {
@@ -83,6 +88,14 @@ impl<BigUint: BigUintApi> NestedEncode for GlobalOpCheckpoint<BigUint> {
                 new_service_fee.dep_encode(dest)?;
                 compute_rewards_data.dep_encode(dest)?;
             }
+            GlobalOpCheckpoint::DistributeRewards(data) => {
+                dest.push_byte(4);
+                data.dep_encode(dest)?;
+            }
+            GlobalOpCheckpoint::SweepDust(data) => {
+                dest.push_byte(5);
+                data.dep_encode(dest)?;
+            }
         }
         Ok(())
     }
@@ -110,6 +123,14 @@ impl<BigUint: BigUintApi> NestedEncode for GlobalOpCheckpoint<BigUint> {
                 new_service_fee.dep_encode_or_exit(dest, c.clone(), exit);
                 compute_rewards_data.dep_encode_or_exit(dest, c.clone(), exit);
             }
+            GlobalOpCheckpoint::DistributeRewards(data) => {
+                dest.push_byte(4);
+                data.dep_encode_or_exit(dest, c.clone(), exit);
+            }
+            GlobalOpCheckpoint::SweepDust(data) => {
+                dest.push_byte(5);
+                data.dep_encode_or_exit(dest, c.clone(), exit);
+            }
         }
     }
 }
@@ -156,6 +177,12 @@ impl<BigUint: BigUintApi> NestedDecode for GlobalOpCheckpoint<BigUint> {
                 new_service_fee: BigUint::dep_decode(input)?,
                 compute_rewards_data: ComputeAllRewardsData::dep_decode(input)?,
             }),
+            4 => Ok(GlobalOpCheckpoint::DistributeRewards(
+                DistributeRewardsData::dep_decode(input)?,
+            )),
+            5 => Ok(GlobalOpCheckpoint::SweepDust(
+                ComputeAllRewardsData::dep_decode(input)?,
+            )),
             _ => Err(DecodeError::INVALID_VALUE),
         }
     }
@@ -180,6 +207,16 @@ impl<BigUint: BigUintApi> NestedDecode for GlobalOpCheckpoint<BigUint> {
                     exit,
                 ),
             },
+            4 => GlobalOpCheckpoint::DistributeRewards(DistributeRewardsData::dep_decode_or_exit(
+                input,
+                c.clone(),
+                exit,
+            )),
+            5 => GlobalOpCheckpoint::SweepDust(ComputeAllRewardsData::dep_decode_or_exit(
+                input,
+                c.clone(),
+                exit,
+            )),
             _ => exit(c, DecodeError::INVALID_VALUE),
         }
     }
@@ -278,6 +315,8 @@ impl<BigUint: BigUintApi> NestedDecode for ModifyTotalDelegationCapData<BigUint>
 /// Models the steps that need to be executed when modifying the total delegation cap.
 #[derive(PartialEq, Debug)]
 pub enum ModifyDelegationCapStep<BigUint: BigUintApi> {
+    /// New operations no longer compute all rewards, the delegation cap change ends the reward regime instead.
+    /// Only kept so that operations in progress during an upgrade can finish.
     ComputeAllRewards(ComputeAllRewardsData<BigUint>),
     SwapWaitingToActive,
     SwapUnstakedToDeferredPayment,
@@ -424,8 +463,105 @@ impl<BigUint: BigUintApi> NestedDecode for ComputeAllRewardsData<BigUint> {
     }
 }
 
+/// Models the interrupted state of a reward distribution.
+#[derive(PartialEq, Debug)]
+pub struct DistributeRewardsData<BigUint: BigUintApi> {
+    pub last_id: usize,
+    pub min_amount: BigUint,
+}
+
+impl<BigUint: BigUintApi> NestedEncode for DistributeRewardsData<BigUint> {
+    fn dep_encode<O: NestedEncodeOutput>(&self, dest: &mut O) -> Result<(), EncodeError> {
+        self.last_id.dep_encode(dest)?;
+        self.min_amount.dep_encode(dest)?;
+        Ok(())
+    }
+
+    #[allow(clippy::redundant_clone)]
+    fn dep_encode_or_exit<O: NestedEncodeOutput, ExitCtx: Clone>(
+        &self,
+        dest: &mut O,
+        c: ExitCtx,
+        exit: fn(ExitCtx, EncodeError) -> !,
+    ) {
+        self.last_id.dep_encode_or_exit(dest, c.clone(), exit);
+        self.min_amount.dep_encode_or_exit(dest, c.clone(), exit);
+    }
+}
+
+impl<BigUint: BigUintApi> NestedDecode for DistributeRewardsData<BigUint> {
+    fn dep_decode<I: NestedDecodeInput>(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(DistributeRewardsData {
+            last_id: usize::dep_decode(input)?,
+            min_amount: BigUint::dep_decode(input)?,
+        })
+    }
+
+    #[allow(clippy::redundant_clone)]
+    fn dep_decode_or_exit<I: NestedDecodeInput, ExitCtx: Clone>(
+        input: &mut I,
+        c: ExitCtx,
+        exit: fn(ExitCtx, DecodeError) -> !,
+    ) -> Self {
+        DistributeRewardsData {
+            last_id: usize::dep_decode_or_exit(input, c.clone(), exit),
+            min_amount: BigUint::dep_decode_or_exit(input, c.clone(), exit),
+        }
+    }
+}
+
+/// A lower service fee, that applies once the total delegation cap reaches a threshold.
+#[derive(PartialEq, Debug)]
+pub struct ServiceFeeTier<BigUint: BigUintApi> {
+    pub min_delegation_cap: BigUint,
+    pub service_fee: BigUint,
+}
+
+impl<BigUint: BigUintApi> NestedEncode for ServiceFeeTier<BigUint> {
+    fn dep_encode<O: NestedEncodeOutput>(&self, dest: &mut O) -> Result<(), EncodeError> {
+        self.min_delegation_cap.dep_encode(dest)?;
+        self.service_fee.dep_encode(dest)?;
+        Ok(())
+    }
+
+    #[allow(clippy::redundant_clone)]
+    fn dep_encode_or_exit<O: NestedEncodeOutput, ExitCtx: Clone>(
+        &self,
+        dest: &mut O,
+        c: ExitCtx,
+        exit: fn(ExitCtx, EncodeError) -> !,
+    ) {
+        self.min_delegation_cap
+            .dep_encode_or_exit(dest, c.clone(), exit);
+        self.service_fee.dep_encode_or_exit(dest, c.clone(), exit);
+    }
+}
+
+impl<BigUint: BigUintApi> NestedDecode for ServiceFeeTier<BigUint> {
+    fn dep_decode<I: NestedDecodeInput>(input: &mut I) -> Result<Self, DecodeError> {
+        Ok(ServiceFeeTier {
+            min_delegation_cap: BigUint::dep_decode(input)?,
+            service_fee: BigUint::dep_decode(input)?,
+        })
+    }
+
+    #[allow(clippy::redundant_clone)]
+    fn dep_decode_or_exit<I: NestedDecodeInput, ExitCtx: Clone>(
+        input: &mut I,
+        c: ExitCtx,
+        exit: fn(ExitCtx, DecodeError) -> !,
+    ) -> Self {
+        ServiceFeeTier {
+            min_delegation_cap: BigUint::dep_decode_or_exit(input, c.clone(), exit),
+            service_fee: BigUint::dep_decode_or_exit(input, c.clone(), exit),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    extern crate alloc;
+
     use super::*;
     use elrond_wasm::elrond_codec::test_util::*;
     use elrond_wasm_debug::*;
@@ -496,5 +632,20 @@ mod tests {
                 rewards_checkpoint: 110u32.into(),
             },
         });
+
+        check_global_operation_checkpoint_codec(GlobalOpCheckpoint::DistributeRewards(
+            DistributeRewardsData {
+                last_id: 108,
+                min_amount: 111u32.into(),
+            },
+        ));
+
+        check_global_operation_checkpoint_codec(GlobalOpCheckpoint::SweepDust(
+            ComputeAllRewardsData {
+                last_id: 108,
+                sum_unclaimed: 109u32.into(),
+                rewards_checkpoint: 110u32.into(),
+            },
+        ));
     }
 }
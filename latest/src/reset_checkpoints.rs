@@ -1,5 +1,7 @@
 use super::elrond_wasm_module_features::*;
 use super::elrond_wasm_module_pause::*;
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
 use super::user_fund_storage::fund_transf_module::*;
 use super::user_fund_storage::fund_view_module::*;
 use super::user_fund_storage::types::*;
@@ -7,7 +9,10 @@ use super::user_fund_storage::user_data::*;
 use crate::reset_checkpoint_types::*;
 use crate::rewards::*;
 use crate::settings::*;
+use crate::time_weighted_rewards::*;
+use crate::user_stake::*;
 use core::cmp::Ordering;
+use core::num::NonZeroUsize;
 
 imports!();
 
@@ -15,6 +20,9 @@ pub const STOP_AT_GASLIMIT: u64 = 100_000_000;
 
 #[elrond_wasm_derive::module(ResetCheckpointsModuleImpl)]
 pub trait ResetCheckpointsModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     #[module(UserDataModuleImpl)]
     fn user_data(&self) -> UserDataModuleImpl<T, BigInt, BigUint>;
 
@@ -36,6 +44,12 @@ pub trait ResetCheckpointsModule {
     #[module(FeaturesModuleImpl)]
     fn features_module(&self) -> FeaturesModuleImpl<T, BigInt, BigUint>;
 
+    #[module(UserStakeModuleImpl)]
+    fn user_stake(&self) -> UserStakeModuleImpl<T, BigInt, BigUint>;
+
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     #[view(getGlobalOperationCheckpoint)]
     #[storage_get("global_op_checkpoint")]
     fn get_global_op_checkpoint(&self) -> Box<GlobalOpCheckpoint<BigUint>>;
@@ -66,7 +80,7 @@ pub trait ResetCheckpointsModule {
     ) -> SCResult<GlobalOpStatus> {
         let mut status = GlobalOpStatus::Done;
         while status.is_done() && !orc.is_none() {
-            let (new_status, new_orc) = self.continue_global_operation_step(orc);
+            let (new_status, new_orc) = sc_try!(self.continue_global_operation_step(orc));
             status = new_status;
             orc = new_orc;
         }
@@ -78,8 +92,8 @@ pub trait ResetCheckpointsModule {
     fn continue_global_operation_step(
         &self,
         orc: Box<GlobalOpCheckpoint<BigUint>>,
-    ) -> (GlobalOpStatus, Box<GlobalOpCheckpoint<BigUint>>) {
-        match *orc {
+    ) -> SCResult<(GlobalOpStatus, Box<GlobalOpCheckpoint<BigUint>>)> {
+        Ok(match *orc {
             GlobalOpCheckpoint::None => (GlobalOpStatus::Done, orc),
             GlobalOpCheckpoint::ModifyTotalDelegationCap(mdcap_data) => {
                 self.continue_modify_total_delegation_cap_step(mdcap_data)
@@ -102,7 +116,27 @@ pub trait ResetCheckpointsModule {
                     (GlobalOpStatus::Done, Box::new(GlobalOpCheckpoint::None))
                 }
             }
-        }
+            GlobalOpCheckpoint::DistributeRewards(dr_data) => {
+                if let Some(more_distribution) = sc_try!(self.distribute_rewards(dr_data)) {
+                    (
+                        GlobalOpStatus::StoppedBeforeOutOfGas,
+                        Box::new(GlobalOpCheckpoint::DistributeRewards(more_distribution)),
+                    )
+                } else {
+                    (GlobalOpStatus::Done, Box::new(GlobalOpCheckpoint::None))
+                }
+            }
+            GlobalOpCheckpoint::SweepDust(car_data) => {
+                if let Some(more_computation) = self.compute_all_rewards(car_data) {
+                    (
+                        GlobalOpStatus::StoppedBeforeOutOfGas,
+                        Box::new(GlobalOpCheckpoint::SweepDust(more_computation)),
+                    )
+                } else {
+                    (GlobalOpStatus::Done, Box::new(GlobalOpCheckpoint::None))
+                }
+            }
+        })
     }
 
     fn continue_modify_total_delegation_cap_step(
@@ -126,9 +160,12 @@ pub trait ResetCheckpointsModule {
                 }
             }
             ModifyDelegationCapStep::SwapWaitingToActive => {
+                self.time_weighted_rewards().update_accumulators();
+                self.rewards().compute_one_user_reward(OWNER_USER_ID);
                 let _ = self.fund_transf_module().swap_waiting_to_active(
                     &mut mdcap_data.remaining_swap_waiting_to_active, // decreases this field directly
                     || self.get_gas_left() < STOP_AT_GASLIMIT,
+                    |user_id| self.compute_reward_before_swap(user_id),
                 );
                 if mdcap_data.remaining_swap_waiting_to_active > 0 {
                     (
@@ -162,9 +199,12 @@ pub trait ResetCheckpointsModule {
                 }
             }
             ModifyDelegationCapStep::SwapActiveToDeferredPayment => {
-                self.fund_transf_module().swap_active_to_deferred_payment(
+                self.time_weighted_rewards().update_accumulators();
+                self.rewards().compute_one_user_reward(OWNER_USER_ID);
+                let _ = self.fund_transf_module().swap_active_to_deferred_payment(
                     &mut mdcap_data.remaining_swap_active_to_def_p, // decreases this field directly
                     || self.get_gas_left() < STOP_AT_GASLIMIT,
+                    |user_id| self.compute_reward_before_swap(user_id),
                 );
                 if mdcap_data.remaining_swap_active_to_def_p > 0 {
                     (
@@ -173,14 +213,32 @@ pub trait ResetCheckpointsModule {
                     )
                 } else {
                     // finish
-                    self.settings()
-                        .set_total_delegation_cap(mdcap_data.new_delegation_cap);
+                    // a lower cap only applies after the stake above it is no longer active
+                    self.change_total_delegation_cap(mdcap_data.new_delegation_cap);
                     (GlobalOpStatus::Done, Box::new(GlobalOpCheckpoint::None))
                 }
             }
         }
     }
 
+    /// Rewards received so far keep being split with the old delegation cap.
+    fn change_total_delegation_cap(&self, new_delegation_cap: BigUint) {
+        if self.settings().get_total_delegation_cap() != new_delegation_cap {
+            self.time_weighted_rewards().update_accumulators();
+            self.rewards().end_reward_regime();
+            self.settings().set_total_delegation_cap(new_delegation_cap);
+        }
+    }
+
+    /// The rewards of a user are computed with their current Active stake,
+    /// so they must be brought up to date before their stake changes, time-weighted or not.
+    /// Called for each fund swapped, within the gas limit of the swap.
+    fn compute_reward_before_swap(&self, user_id: usize) {
+        if let Some(user_id_nz) = NonZeroUsize::new(user_id) {
+            self.rewards().compute_one_user_reward(user_id_nz);
+        }
+    }
+
     /// When there is a change of the base cap from which the rewards are computed,
     /// the checkpoints must be reset for all the delegators.
     /// This process might be longer then one block - reaching the gaslimit
@@ -216,26 +274,93 @@ pub trait ResetCheckpointsModule {
             data.last_id = current_user_id.get();
         }
 
+        // the service fee kept apart is computed the same way as the user rewards
+        let sf_data = self.rewards().compute_service_fee_rewards();
+        data.sum_unclaimed += sf_data.unclaimed_rewards;
+
         // divisions are inexact so a small remainder can remain after distributing rewards
         // give it to the owner, to keep things clear
         let remainder = &self.rewards().get_total_cumulated_rewards()
             - &data.sum_unclaimed
             - self.rewards().get_sent_rewards();
-        if remainder > 0 {
-            let mut node_unclaimed = self.rewards().get_user_rew_unclaimed(OWNER_USER_ID);
-            node_unclaimed += &remainder;
-            self.rewards()
-                .set_user_rew_unclaimed(OWNER_USER_ID, &node_unclaimed);
-        }
+        self.rewards().add_dust_to_owner(&remainder);
 
         None
     }
 
+    /// Adds the rounding dust to the owner rewards.
+    /// Computes the rewards of all users to find it,
+    /// which can take several blocks, in which case it continues via `continueGlobalOperation`.
+    #[endpoint(sweepDust)]
+    fn sweep_dust(&self) -> SCResult<GlobalOpStatus> {
+        only_delegation_owner!(self, "only owner can sweep dust");
+        require!(
+            !self.is_global_op_in_progress(),
+            "global checkpoint is in progress"
+        );
+
+        let rewards_checkpoint = self.rewards().get_total_cumulated_rewards();
+        self.continue_global_operation(Box::new(GlobalOpCheckpoint::SweepDust(
+            ComputeAllRewardsData::new(rewards_checkpoint),
+        )))
+    }
+
+    /// Sends all delegators their rewards, so they don't have to claim them.
+    /// Users with less than `min_amount` in rewards are skipped, to save gas.
+    /// The rewards of users that opted for auto-compounding are re-delegated instead,
+    /// or skipped and reported in an event if they cannot be.
+    /// Might take several blocks, in which case it continues via `continueGlobalOperation`.
+    #[endpoint(distributeRewards)]
+    fn distribute_rewards_endpoint(&self, min_amount: BigUint) -> SCResult<GlobalOpStatus> {
+        only_delegation_owner!(self, "only owner can distribute rewards");
+        require!(self.pause().not_paused(), "contract paused");
+        require!(
+            !self.is_global_op_in_progress(),
+            "global checkpoint is in progress"
+        );
+
+        self.continue_global_operation(Box::new(GlobalOpCheckpoint::DistributeRewards(
+            DistributeRewardsData {
+                last_id: 0,
+                min_amount,
+            },
+        )))
+    }
+
+    /// Returns something if there are more users to go through.
+    fn distribute_rewards(
+        &self,
+        mut data: DistributeRewardsData<BigUint>,
+    ) -> SCResult<Option<DistributeRewardsData<BigUint>>> {
+        let num_users = self.user_data().get_num_users();
+        while data.last_id < num_users {
+            if self.get_gas_left() < STOP_AT_GASLIMIT {
+                return Ok(Some(data));
+            }
+
+            let current_user_id = non_zero_usize_from_n_plus_1(data.last_id);
+            let user_data = self.rewards().load_updated_user_rewards(current_user_id);
+            if user_data.unclaimed_rewards > 0 && user_data.unclaimed_rewards >= data.min_amount {
+                let user_address = self.user_data().get_user_address(current_user_id.get());
+                sc_try!(self.rewards().claim_user_rewards(
+                    current_user_id,
+                    &user_address,
+                    user_data
+                ));
+            }
+            data.last_id = current_user_id.get();
+        }
+
+        Ok(None)
+    }
+
     /// Total delegation cap can be modified by owner only.
-    /// It will recalculate and set the checkpoint for all the delegators
+    /// Rewards are not recomputed for all delegators, the change ends the reward regime instead.
+    /// Stake still has to be activated or deactivated to match the new cap,
+    /// which can take several blocks, in which case it continues via `continueGlobalOperation`.
     #[endpoint(modifyTotalDelegationCap)]
     fn modify_total_delegation_cap(&self, new_total_cap: BigUint) -> SCResult<GlobalOpStatus> {
-        only_owner!(self, "only owner allowed to modify delegation cap");
+        only_delegation_owner!(self, "only owner allowed to modify delegation cap");
 
         require!(
             !self.is_global_op_in_progress(),
@@ -260,6 +385,7 @@ pub trait ResetCheckpointsModule {
                 // and so nothing else to be done here:
                 // compute all rewards not necessary - no rewards yet
                 // swap not necessary - there cannot be any waiting or unstaked funds
+                self.time_weighted_rewards().update_accumulators();
                 self.settings().set_total_delegation_cap(new_total_cap);
                 return Ok(GlobalOpStatus::Done);
             } else {
@@ -298,17 +424,18 @@ pub trait ResetCheckpointsModule {
                 );
 
                 let swap_amount = &new_total_cap - &previous_total_cap;
+
+                // a higher cap applies before more stake becomes active,
+                // so the Active stake never exceeds the cap
+                self.change_total_delegation_cap(new_total_cap.clone());
+
                 Box::new(GlobalOpCheckpoint::ModifyTotalDelegationCap(
                     ModifyTotalDelegationCapData {
                         new_delegation_cap: new_total_cap,
                         remaining_swap_waiting_to_active: swap_amount,
                         remaining_swap_active_to_def_p: BigUint::zero(),
                         remaining_swap_unstaked_to_def_p: BigUint::zero(),
-                        step: ModifyDelegationCapStep::ComputeAllRewards(
-                            ComputeAllRewardsData::new(
-                                self.rewards().get_total_cumulated_rewards(),
-                            ),
-                        ),
+                        step: ModifyDelegationCapStep::SwapWaitingToActive,
                     },
                 ))
             }
@@ -338,11 +465,7 @@ pub trait ResetCheckpointsModule {
                         remaining_swap_waiting_to_active: BigUint::zero(),
                         remaining_swap_active_to_def_p: swap_active_to_def_p,
                         remaining_swap_unstaked_to_def_p: swap_unstaked_to_def_p,
-                        step: ModifyDelegationCapStep::ComputeAllRewards(
-                            ComputeAllRewardsData::new(
-                                self.rewards().get_total_cumulated_rewards(),
-                            ),
-                        ),
+                        step: ModifyDelegationCapStep::SwapWaitingToActive,
                     },
                 ))
             }
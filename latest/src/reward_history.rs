@@ -0,0 +1,183 @@
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
+use super::settings::*;
+use super::user_fund_storage::fund_view_module::*;
+use super::user_fund_storage::types::*;
+use crate::rewards::*;
+
+imports!();
+
+/// Approximate number of blocks in a year, with 6 second rounds.
+pub const BLOCKS_PER_YEAR: u64 = 5_256_000;
+
+/// How many periods the APR estimate looks back, if not specified.
+pub const DEFAULT_APR_NUM_PERIODS: usize = 7;
+
+/// Keeps snapshots of the cumulated rewards and the total active stake,
+/// at most one for every period of `n` blocks.
+/// Rewards arrive without calling the contract, so snapshots are only taken
+/// when claiming rewards or when someone calls `updateRewardHistory`.
+/// A snapshot records the block nonce it was actually taken at,
+/// so periods where no snapshot was taken simply get merged into the next one.
+#[elrond_wasm_derive::module(RewardHistoryModuleImpl)]
+pub trait RewardHistoryModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(RewardsModuleImpl)]
+    fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(SettingsModuleImpl)]
+    fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(FundViewModuleImpl)]
+    fn fund_view_module(&self) -> FundViewModuleImpl<T, BigInt, BigUint>;
+
+    /// Length of a reward history period, in blocks.
+    /// 0 means no history is kept.
+    #[view(getRewardHistoryPeriod)]
+    #[storage_get("rh_period")]
+    fn get_reward_history_period(&self) -> u64;
+
+    #[storage_set("rh_period")]
+    fn set_reward_history_period(&self, n_blocks: u64);
+
+    #[endpoint(setRewardHistoryPeriod)]
+    fn set_reward_history_period_endpoint(&self, n_blocks: u64) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can set reward history period");
+        self.set_reward_history_period(n_blocks);
+        Ok(())
+    }
+
+    #[view(getRewardHistoryLength)]
+    #[storage_get("rh_len")]
+    fn get_reward_history_len(&self) -> usize;
+
+    #[storage_set("rh_len")]
+    fn set_reward_history_len(&self, len: usize);
+
+    #[storage_get("rh_nonce")]
+    fn get_snapshot_block_nonce(&self, index: usize) -> u64;
+
+    #[storage_set("rh_nonce")]
+    fn set_snapshot_block_nonce(&self, index: usize, block_nonce: u64);
+
+    #[storage_get("rh_cumul")]
+    fn get_snapshot_cumulated_rewards(&self, index: usize) -> BigUint;
+
+    #[storage_set("rh_cumul")]
+    fn set_snapshot_cumulated_rewards(&self, index: usize, cumulated_rewards: &BigUint);
+
+    #[storage_get("rh_stake")]
+    fn get_snapshot_total_active_stake(&self, index: usize) -> BigUint;
+
+    #[storage_set("rh_stake")]
+    fn set_snapshot_total_active_stake(&self, index: usize, total_active_stake: &BigUint);
+
+    /// Takes a snapshot, unless history is disabled or there already is one for the current period.
+    fn update_reward_history(&self) {
+        let period = self.get_reward_history_period();
+        if period == 0 {
+            return;
+        }
+
+        let block_nonce = self.get_block_nonce();
+        let len = self.get_reward_history_len();
+        if len > 0 && self.get_snapshot_block_nonce(len - 1) / period == block_nonce / period {
+            return;
+        }
+
+        self.set_snapshot_block_nonce(len, block_nonce);
+        self.set_snapshot_cumulated_rewards(len, &self.rewards().get_total_cumulated_rewards());
+        self.set_snapshot_total_active_stake(
+            len,
+            &self
+                .fund_view_module()
+                .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active),
+        );
+        self.set_reward_history_len(len + 1);
+    }
+
+    /// Anyone can call this, to make sure the current period gets a snapshot.
+    #[endpoint(updateRewardHistory)]
+    fn update_reward_history_endpoint(&self) -> SCResult<()> {
+        require!(
+            self.get_reward_history_period() > 0,
+            "reward history disabled"
+        );
+        self.update_reward_history();
+        Ok(())
+    }
+
+    /// Yields the block nonce, the cumulated rewards and the total active stake at the time of the snapshot.
+    #[view(getRewardSnapshot)]
+    fn get_reward_snapshot(&self, index: usize) -> SCResult<MultiResult3<u64, BigUint, BigUint>> {
+        require!(
+            index < self.get_reward_history_len(),
+            "reward snapshot not found"
+        );
+        Ok(MultiResult3::from((
+            self.get_snapshot_block_nonce(index),
+            self.get_snapshot_cumulated_rewards(index),
+            self.get_snapshot_total_active_stake(index),
+        )))
+    }
+
+    /// Yields the first block nonce, the last block nonce and the rewards received
+    /// for each interval between consecutive snapshots, oldest first.
+    /// Only the last `max_count` intervals are returned, if specified.
+    #[view(getRewardsPerPeriod)]
+    fn get_rewards_per_period(
+        &self,
+        #[var_args] max_count: OptionalArg<usize>,
+    ) -> MultiResultVec<MultiResult3<u64, u64, BigUint>> {
+        let len = self.get_reward_history_len();
+        let num_intervals = len.saturating_sub(1);
+        let count = match max_count.into_option() {
+            Some(max_count) if max_count < num_intervals => max_count,
+            _ => num_intervals,
+        };
+
+        let mut result = Vec::new();
+        for index in (len - count)..len {
+            let start_nonce = self.get_snapshot_block_nonce(index - 1);
+            let end_nonce = self.get_snapshot_block_nonce(index);
+            let rewards = self.get_snapshot_cumulated_rewards(index)
+                - self.get_snapshot_cumulated_rewards(index - 1);
+            result.push(MultiResult3::from((start_nonce, end_nonce, rewards)));
+        }
+        result.into()
+    }
+
+    /// Estimated yearly yield for delegators, 10000 = 100%, based on the last `num_periods` intervals.
+    /// Delegators get rewards relative to the total delegation cap,
+    /// whatever is missing from the active stake goes to the owner,
+    /// so the yield of the active stake is the delegator part of the rewards over the cap.
+    /// The current service fee and delegation cap are used.
+    /// Yields 0 if there is not enough history.
+    #[view(getEstimatedAPR)]
+    fn get_estimated_apr(&self, #[var_args] num_periods: OptionalArg<usize>) -> BigUint {
+        let len = self.get_reward_history_len();
+        let num_periods = num_periods.into_option().unwrap_or(DEFAULT_APR_NUM_PERIODS);
+        if len < 2 || num_periods == 0 {
+            return BigUint::zero();
+        }
+
+        let last = len - 1;
+        let first = last.saturating_sub(num_periods);
+        let n_blocks = self.get_snapshot_block_nonce(last) - self.get_snapshot_block_nonce(first);
+        let delegation_cap = self.settings().get_total_delegation_cap();
+        if n_blocks == 0 || delegation_cap == 0 {
+            return BigUint::zero();
+        }
+
+        let rewards =
+            self.get_snapshot_cumulated_rewards(last) - self.get_snapshot_cumulated_rewards(first);
+        let (_, mut apr) = self.rewards().split_service_reward(&rewards);
+        apr *= &BigUint::from(PERCENTAGE_DENOMINATOR);
+        apr *= &BigUint::from(BLOCKS_PER_YEAR);
+        apr /= &delegation_cap;
+        apr /= &BigUint::from(n_blocks);
+        apr
+    }
+}
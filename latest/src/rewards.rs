@@ -3,16 +3,30 @@ use super::user_fund_storage::types::fund_type::*;
 use super::elrond_wasm_module_features::*;
 use super::elrond_wasm_module_pause::*;
 use super::node_storage::node_config::*;
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
 use super::settings::*;
 use super::user_fund_storage::fund_transf_module::*;
 use super::user_fund_storage::fund_view_module::*;
 use super::user_fund_storage::user_data::*;
 use crate::events::*;
+use crate::reset_checkpoint_types::*;
 use crate::reset_checkpoints::*;
+use crate::reward_history::*;
+use crate::time_weighted_rewards::*;
+use crate::user_stake::*;
 use core::num::NonZeroUsize;
 
 imports!();
 
+/// Rewards received while the same service fee and delegation cap were in effect, already split.
+pub struct RewardSlice<BigUint> {
+    pub service_rewards: BigUint,
+    pub total_delegators_rewards: BigUint,
+    pub delegation_cap: BigUint,
+    pub total_active_stake: BigUint,
+}
+
 // Groups together data per delegator from the storage.
 pub struct UserRewardData<BigUint> {
     /// The value of the total cumulated rewards in the contract when the user's rewards were computed the last time.
@@ -25,6 +39,9 @@ pub struct UserRewardData<BigUint> {
 /// Contains logic to compute and distribute individual delegator rewards.
 #[elrond_wasm_derive::module(RewardsModuleImpl)]
 pub trait RewardsModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     #[module(EventsModuleImpl)]
     fn events(&self) -> EventsModuleImpl<T, BigInt, BigUint>;
 
@@ -40,6 +57,15 @@ pub trait RewardsModule {
     #[module(ResetCheckpointsModuleImpl)]
     fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
 
+    #[module(RewardHistoryModuleImpl)]
+    fn reward_history(&self) -> RewardHistoryModuleImpl<T, BigInt, BigUint>;
+
+    #[module(UserStakeModuleImpl)]
+    fn user_stake(&self) -> UserStakeModuleImpl<T, BigInt, BigUint>;
+
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     #[module(FundTransformationsModuleImpl)]
     fn fund_transf_module(&self) -> FundTransformationsModuleImpl<T, BigInt, BigUint>;
 
@@ -77,6 +103,22 @@ pub trait RewardsModule {
     #[storage_set("u_rew_checkp")]
     fn set_user_rew_checkpoint(&self, user_id: NonZeroUsize, user_rew_checkpoint: &BigUint);
 
+    /// Total rewards ever sent to the user through claimRewards.
+    #[storage_get("u_rew_claimed")]
+    fn get_user_rew_claimed(&self, user_id: NonZeroUsize) -> BigUint;
+
+    #[storage_set("u_rew_claimed")]
+    fn set_user_rew_claimed(&self, user_id: NonZeroUsize, user_rew_claimed: &BigUint);
+
+    /// Block nonce of the last claimRewards that sent something to the user.
+    /// If zero, the user never claimed any rewards.
+    #[storage_get("u_last_claim")]
+    fn get_user_last_claim_nonce(&self, user_id: NonZeroUsize) -> u64;
+
+    #[storage_set("u_last_claim")]
+    fn set_user_last_claim_nonce(&self, user_id: NonZeroUsize, block_nonce: u64);
+
+    /// Rewards that left the reward pool, either sent to users or re-delegated.
     #[storage_get("sent_rewards")]
     fn get_sent_rewards(&self) -> BigUint;
 
@@ -96,14 +138,26 @@ pub trait RewardsModule {
     }
 
     /// The account running the nodes is entitled to (service_fee / NODE_DENOMINATOR) * rewards.
+    /// The service fee is the one of the tier currently in effect.
     /// Yields the service reward and the non-service-reward.
+    fn split_service_reward(&self, tot_rewards: &BigUint) -> (BigUint, BigUint) {
+        self.split_service_reward_with_fee(
+            tot_rewards,
+            &self.settings().get_effective_service_fee(),
+        )
+    }
+
+    /// Splits rewards into the service reward and the non-service-reward.
     ///
     /// The sum of the 2 outputs is <= tot_rewards (not always equal).
     /// Both results are rounded down,
     /// so te rounding error is not in the result.
     /// This is deliberate, to avoid a very subtle rounding error edge case.
-    fn split_service_reward(&self, tot_rewards: &BigUint) -> (BigUint, BigUint) {
-        let service_fee = &self.settings().get_service_fee();
+    fn split_service_reward_with_fee(
+        &self,
+        tot_rewards: &BigUint,
+        service_fee: &BigUint,
+    ) -> (BigUint, BigUint) {
         let perc_denominator = &BigUint::from(PERCENTAGE_DENOMINATOR);
 
         // part of the rewards that goes to the owner
@@ -118,59 +172,276 @@ pub trait RewardsModule {
         (service_rewards, total_delegators_rewards)
     }
 
+    // REWARD REGIMES
+
+    /// A reward regime is a period during which the service fee and the delegation cap did not change.
+    /// Only regimes that are over are saved, the current one uses the settings directly.
+    /// Each saved regime keeps the total cumulated rewards when it ended, the fee and cap in effect,
+    /// and the total Active stake when it ended.
+    /// This way the service fee and the delegation cap can change without computing the rewards of all users first:
+    /// rewards are split with the parameters in effect when they arrived, when a user's rewards are computed.
+    /// Changing them only adds one regime, and a regime is found with a binary search.
+    ///
+    /// The Active stake can change within a regime, but the rewards of the owner and of the users involved
+    /// are always computed right before, so the part of a regime not yet computed
+    /// was earned by the Active stake at the end of the regime.
+    #[view(getNumRewardRegimes)]
+    #[storage_get("rr_len")]
+    fn get_num_reward_regimes(&self) -> usize;
+
+    #[storage_set("rr_len")]
+    fn set_num_reward_regimes(&self, num_regimes: usize);
+
+    #[storage_get("rr_end")]
+    fn get_reward_regime_end(&self, index: usize) -> BigUint;
+
+    #[storage_set("rr_end")]
+    fn set_reward_regime_end(&self, index: usize, cumulated_rewards: &BigUint);
+
+    #[storage_get("rr_fee")]
+    fn get_reward_regime_service_fee(&self, index: usize) -> BigUint;
+
+    #[storage_set("rr_fee")]
+    fn set_reward_regime_service_fee(&self, index: usize, service_fee: &BigUint);
+
+    #[storage_get("rr_cap")]
+    fn get_reward_regime_delegation_cap(&self, index: usize) -> BigUint;
+
+    #[storage_set("rr_cap")]
+    fn set_reward_regime_delegation_cap(&self, index: usize, delegation_cap: &BigUint);
+
+    #[storage_get("rr_active")]
+    fn get_reward_regime_total_active_stake(&self, index: usize) -> BigUint;
+
+    #[storage_set("rr_active")]
+    fn set_reward_regime_total_active_stake(&self, index: usize, total_active_stake: &BigUint);
+
+    /// Yields the total cumulated rewards when the regime ended, the service fee, the delegation cap
+    /// and the total Active stake.
+    #[view(getRewardRegime)]
+    fn get_reward_regime(
+        &self,
+        index: usize,
+    ) -> SCResult<MultiResult4<BigUint, BigUint, BigUint, BigUint>> {
+        require!(
+            index < self.get_num_reward_regimes(),
+            "reward regime not found"
+        );
+        Ok(MultiResult4::from((
+            self.get_reward_regime_end(index),
+            self.get_reward_regime_service_fee(index),
+            self.get_reward_regime_delegation_cap(index),
+            self.get_reward_regime_total_active_stake(index),
+        )))
+    }
+
+    fn get_total_active_stake(&self) -> BigUint {
+        self.fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active)
+    }
+
+    /// Must be called right before the effective service fee or the delegation cap change.
+    fn end_reward_regime(&self) {
+        let tot_cumul_rewards = self.get_total_cumulated_rewards();
+        let num_regimes = self.get_num_reward_regimes();
+        let last_end = if num_regimes > 0 {
+            self.get_reward_regime_end(num_regimes - 1)
+        } else {
+            BigUint::zero()
+        };
+        if tot_cumul_rewards == last_end {
+            // no rewards arrived in the current regime, nothing to remember
+            return;
+        }
+
+        self.set_reward_regime_end(num_regimes, &tot_cumul_rewards);
+        self.set_reward_regime_service_fee(
+            num_regimes,
+            &self.settings().get_effective_service_fee(),
+        );
+        self.set_reward_regime_delegation_cap(
+            num_regimes,
+            &self.settings().get_total_delegation_cap(),
+        );
+        self.set_reward_regime_total_active_stake(num_regimes, &self.get_total_active_stake());
+        self.set_num_reward_regimes(num_regimes + 1);
+    }
+
+    /// Index of the first regime that ended after the given cumulated rewards value,
+    /// or the number of saved regimes if they all ended before, i.e. the current regime.
+    fn find_reward_regime(&self, cumulated_rewards: &BigUint) -> usize {
+        let mut low = 0;
+        let mut high = self.get_num_reward_regimes();
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.get_reward_regime_end(mid) > *cumulated_rewards {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        low
+    }
+
+    /// Splits the rewards received between the 2 cumulated rewards values,
+    /// with one slice for each reward regime involved.
+    /// With a single regime, the result is the same as splitting all the rewards at once.
+    fn split_rewards_between(&self, from: &BigUint, to: &BigUint) -> Vec<RewardSlice<BigUint>> {
+        let mut slices = Vec::new();
+        let mut start = from.clone();
+        let num_regimes = self.get_num_reward_regimes();
+        let mut regime_index = self.find_reward_regime(from);
+        while regime_index < num_regimes && start < *to {
+            let regime_end = self.get_reward_regime_end(regime_index);
+            let end = if regime_end < *to {
+                regime_end
+            } else {
+                to.clone()
+            };
+            let (service_rewards, total_delegators_rewards) = self.split_service_reward_with_fee(
+                &(&end - &start),
+                &self.get_reward_regime_service_fee(regime_index),
+            );
+            slices.push(RewardSlice {
+                service_rewards,
+                total_delegators_rewards,
+                delegation_cap: self.get_reward_regime_delegation_cap(regime_index),
+                total_active_stake: self.get_reward_regime_total_active_stake(regime_index),
+            });
+            start = end;
+            regime_index += 1;
+        }
+
+        if start < *to {
+            let (service_rewards, total_delegators_rewards) =
+                self.split_service_reward(&(to - &start));
+            slices.push(RewardSlice {
+                service_rewards,
+                total_delegators_rewards,
+                delegation_cap: self.settings().get_total_delegation_cap(),
+                total_active_stake: self.get_total_active_stake(),
+            });
+        }
+
+        slices
+    }
+
     /// Does not update storage, only returns the user rewards object, after computing rewards.
     fn load_updated_user_rewards(&self, user_id: NonZeroUsize) -> UserRewardData<BigUint> {
         let mut user_data = self.load_user_reward_data(user_id);
 
         // new rewards are what was added since the last time rewards were computed
         let tot_cumul_rewards = self.get_total_cumulated_rewards();
-        let tot_new_rewards = &tot_cumul_rewards - &user_data.reward_checkpoint;
-        if tot_new_rewards == 0 {
+
+        // time-weighted rewards can be due without new rewards, when the period of earlier ones ends
+        if self.time_weighted_rewards().is_time_weighted_rewards() {
+            // rewards received before the switch to time-weighted rewards,
+            // the Active stake of the user did not change since
+            let switch_checkpoint = self.time_weighted_rewards().get_switch_rewards_checkpoint();
+            if user_data.reward_checkpoint < switch_checkpoint {
+                for slice in self
+                    .split_rewards_between(&user_data.reward_checkpoint, &switch_checkpoint)
+                    .iter()
+                {
+                    user_data.unclaimed_rewards += self.active_stake_rewards(
+                        user_id,
+                        &slice.total_delegators_rewards,
+                        &slice.delegation_cap,
+                    );
+                }
+            }
+
+            // the service fee does not depend on stake, so it is computed the same way
+            if user_id == OWNER_USER_ID && self.is_empty_service_fee_destination() {
+                for slice in self
+                    .split_rewards_between(&user_data.reward_checkpoint, &tot_cumul_rewards)
+                    .iter()
+                {
+                    user_data.unclaimed_rewards += &slice.service_rewards;
+                }
+            }
+
+            let (stake_rewards, missing_stake_rewards) = self
+                .time_weighted_rewards()
+                .load_accumulated_user_rewards(user_id);
+            user_data.unclaimed_rewards += stake_rewards;
+            user_data.unclaimed_rewards += missing_stake_rewards;
+
+            user_data.reward_checkpoint = tot_cumul_rewards;
+            return user_data;
+        }
+
+        if tot_cumul_rewards == user_data.reward_checkpoint {
             return user_data; // nothing happened since the last claim
         }
 
         // the owner is entitled to: tot_new_rewards * service_fee / NODE_DENOMINATOR
         // delegators are entitled to: tot_new_rewards * (1 - service_fee / NODE_DENOMINATOR)
-        let (service_rewards, total_delegators_rewards) =
-            self.split_service_reward(&tot_new_rewards);
-
-        let delegation_cap = self.settings().get_total_delegation_cap();
+        // each with the service fee in effect when the rewards arrived
+        for slice in self
+            .split_rewards_between(&user_data.reward_checkpoint, &tot_cumul_rewards)
+            .iter()
+        {
+            // update node rewards, if applicable
+            if user_id == OWNER_USER_ID {
+                // the owner gets the service fee, unless it goes to a separate destination
+                if self.is_empty_service_fee_destination() {
+                    user_data.unclaimed_rewards += &slice.service_rewards;
+                }
 
-        // update node rewards, if applicable
-        if user_id == OWNER_USER_ID {
-            // the owner gets the service fee
-            user_data.unclaimed_rewards += &service_rewards;
-
-            // the owner gets the rewards for the missing active (unstaked) stake
-            let tot_stake_active = self
-                .fund_view_module()
-                .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active);
-            if tot_stake_active < delegation_cap {
-                let missing_stake = &delegation_cap - &tot_stake_active;
-                let mut owner_unstaked_rewards = total_delegators_rewards.clone();
-                owner_unstaked_rewards *= &missing_stake;
-                owner_unstaked_rewards /= &delegation_cap;
-                user_data.unclaimed_rewards += &owner_unstaked_rewards;
+                // the owner gets the rewards for the missing active (unstaked) stake
+                user_data.unclaimed_rewards += self.missing_stake_rewards(slice);
             }
+
+            // update delegator rewards based on Active stake
+            user_data.unclaimed_rewards += self.active_stake_rewards(
+                user_id,
+                &slice.total_delegators_rewards,
+                &slice.delegation_cap,
+            );
+        }
+
+        // update user data checkpoint
+        user_data.reward_checkpoint = tot_cumul_rewards;
+
+        user_data
+    }
+
+    /// Part of the delegator rewards that corresponds to the stake missing from the delegation cap,
+    /// with the total Active stake of the regime.
+    /// This goes to the owner.
+    fn missing_stake_rewards(&self, slice: &RewardSlice<BigUint>) -> BigUint {
+        if slice.total_active_stake < slice.delegation_cap {
+            let missing_stake = &slice.delegation_cap - &slice.total_active_stake;
+            let mut owner_unstaked_rewards = slice.total_delegators_rewards.clone();
+            owner_unstaked_rewards *= &missing_stake;
+            owner_unstaked_rewards /= &slice.delegation_cap;
+            owner_unstaked_rewards
+        } else {
+            BigUint::zero()
         }
+    }
 
-        // update delegator rewards based on Active stake
+    /// Part of the delegator rewards that corresponds to the Active stake of a user.
+    fn active_stake_rewards(
+        &self,
+        user_id: NonZeroUsize,
+        total_delegators_rewards: &BigUint,
+        delegation_cap: &BigUint,
+    ) -> BigUint {
         let u_stake_active = self
             .fund_view_module()
             .get_user_stake_of_type(user_id.get(), FundType::Active);
         if u_stake_active > 0 {
             // delegator reward is:
             // total new rewards * (1 - service_fee / NODE_DENOMINATOR) * user stake / total delegation cap
-            let mut delegator_new_rewards = total_delegators_rewards;
+            let mut delegator_new_rewards = total_delegators_rewards.clone();
             delegator_new_rewards *= &u_stake_active;
-            delegator_new_rewards /= &delegation_cap;
-            user_data.unclaimed_rewards += &delegator_new_rewards;
+            delegator_new_rewards /= delegation_cap;
+            delegator_new_rewards
+        } else {
+            BigUint::zero()
         }
-
-        // update user data checkpoint
-        user_data.reward_checkpoint = tot_cumul_rewards;
-
-        user_data
     }
 
     /// Convenience method, brings user rewards up to date for one user.
@@ -191,6 +462,48 @@ pub trait RewardsModule {
         }
     }
 
+    /// Yields the total rewards claimed by a user so far, and the block nonce of the last claim.
+    #[view(getUserClaimedRewards)]
+    fn get_user_claimed_rewards(&self, user: Address) -> MultiResult2<BigUint, u64> {
+        if let Some(user_id) = NonZeroUsize::new(self.user_data().get_user_id(&user)) {
+            MultiResult2::from((
+                self.get_user_rew_claimed(user_id),
+                self.get_user_last_claim_nonce(user_id),
+            ))
+        } else {
+            MultiResult2::from((BigUint::zero(), 0))
+        }
+    }
+
+    /// Yields, in this order:
+    /// - the total rewards claimed so far,
+    /// - the unclaimed rewards, as of the last time the user rewards were computed,
+    /// - the rewards that can be claimed right now,
+    /// - the reward checkpoint, i.e. the total cumulated rewards when the user rewards were last computed.
+    #[view(getUserRewardSummary)]
+    fn get_user_reward_summary(
+        &self,
+        user: Address,
+    ) -> MultiResult4<BigUint, BigUint, BigUint, BigUint> {
+        if let Some(user_id) = NonZeroUsize::new(self.user_data().get_user_id(&user)) {
+            let stored_data = self.load_user_reward_data(user_id);
+            let updated_data = self.load_updated_user_rewards(user_id);
+            MultiResult4::from((
+                self.get_user_rew_claimed(user_id),
+                stored_data.unclaimed_rewards,
+                updated_data.unclaimed_rewards,
+                stored_data.reward_checkpoint,
+            ))
+        } else {
+            MultiResult4::from((
+                BigUint::zero(),
+                BigUint::zero(),
+                BigUint::zero(),
+                BigUint::zero(),
+            ))
+        }
+    }
+
     /// Utility readonly function to check how many unclaimed rewards currently reside in the contract.
     #[view(getTotalUnclaimedRewards)]
     fn get_total_unclaimed_rewards(&self) -> BigUint {
@@ -203,13 +516,49 @@ pub trait RewardsModule {
             sum_unclaimed += user_data.unclaimed_rewards;
         }
 
+        // service fee kept apart from the owner rewards
+        sum_unclaimed += self.load_updated_service_fee_rewards().unclaimed_rewards;
+
         sum_unclaimed
     }
 
+    /// Rewards that arrived in the contract but that nobody can claim,
+    /// because divisions round down when splitting them.
+    /// They are what remains after subtracting the rewards sent and all unclaimed rewards.
+    /// Iterates over all users.
+    #[view(getUndistributedDust)]
+    fn get_undistributed_dust(&self) -> BigUint {
+        let available = self.get_total_cumulated_rewards() - self.get_sent_rewards();
+        let sum_unclaimed = self.get_total_unclaimed_rewards();
+        if available > sum_unclaimed {
+            available - sum_unclaimed
+        } else {
+            BigUint::zero()
+        }
+    }
+
+    /// Adds the rounding dust to the owner rewards and to the owner income.
+    fn add_dust_to_owner(&self, dust: &BigUint) {
+        if *dust == 0 {
+            return;
+        }
+
+        let mut owner_unclaimed = self.get_user_rew_unclaimed(OWNER_USER_ID);
+        owner_unclaimed += dust;
+        self.set_user_rew_unclaimed(OWNER_USER_ID, &owner_unclaimed);
+
+        let mut dust_income = self.get_owner_dust_income();
+        dust_income += dust;
+        self.set_owner_dust_income(&dust_income);
+
+        self.events().dust_swept_event(dust);
+    }
+
     /// Retrieve those rewards to which the caller is entitled.
     /// Will send:
     /// - new rewards
     /// - rewards that were previously computed but not sent
+    /// Rewards are sent to the caller even if they opted for auto-compounding.
     #[endpoint(claimRewards)]
     fn claim_rewards(&self) -> SCResult<()> {
         require!(self.pause().not_paused(), "contract paused");
@@ -223,20 +572,107 @@ pub trait RewardsModule {
             "claim rewards is temporarily paused as checkpoint is reset"
         );
 
-        let mut user_data = self.load_updated_user_rewards(user_id);
+        self.reward_history().update_reward_history();
+
+        let user_data = self.load_updated_user_rewards(user_id);
+        self.send_user_rewards(user_id, &caller, user_data);
 
+        Ok(())
+    }
+
+    /// Sends the user all their rewards,
+    /// or re-delegates them, if the user opted for auto-compounding.
+    /// Used when the rewards are claimed or distributed on behalf of the user.
+    /// Rewards that cannot be re-delegated stay unclaimed and the user is reported in an event,
+    /// so the other users still get theirs.
+    fn claim_user_rewards(
+        &self,
+        user_id: NonZeroUsize,
+        user_address: &Address,
+        user_data: UserRewardData<BigUint>,
+    ) -> SCResult<()> {
+        if self.user_stake().is_auto_compound(user_id) {
+            if user_data.unclaimed_rewards > 0
+                && self
+                    .user_stake()
+                    .validate_delegation_cap_invariant()
+                    .is_err()
+            {
+                self.events()
+                    .claim_rewards_skipped_event(user_address, &user_data.unclaimed_rewards);
+                return Ok(());
+            }
+
+            // any error left means the contract storage is inconsistent, so the whole transaction fails
+            sc_try!(self
+                .user_stake()
+                .re_delegate_user_rewards(user_id, user_address));
+            return Ok(());
+        }
+
+        self.send_user_rewards(user_id, user_address, user_data);
+
+        Ok(())
+    }
+
+    /// Sends the computed unclaimed rewards and saves the user reward data.
+    fn send_user_rewards(
+        &self,
+        user_id: NonZeroUsize,
+        user_address: &Address,
+        mut user_data: UserRewardData<BigUint>,
+    ) {
         if user_data.unclaimed_rewards > 0 {
             self.events()
-                .claim_rewards_event(&caller, &user_data.unclaimed_rewards);
+                .claim_rewards_event(user_address, &user_data.unclaimed_rewards);
 
-            self.send_rewards(&caller, &user_data.unclaimed_rewards);
+            self.send_rewards(user_address, &user_data.unclaimed_rewards);
+            self.record_user_claim(user_id, &user_data.unclaimed_rewards);
 
             user_data.unclaimed_rewards = BigUint::zero();
         }
 
         self.store_user_reward_data(user_id, &user_data);
+    }
 
-        Ok(())
+    /// Claims rewards on behalf of the given users. Anyone can call it.
+    /// Each user gets their own rewards, or has them re-delegated if they opted for auto-compounding.
+    /// Addresses that are not delegators are skipped.
+    /// Users whose rewards cannot be re-delegated are skipped and reported in an event.
+    /// Stops before running out of gas.
+    /// Yields the status and how many of the addresses were processed.
+    /// If stopped, call again with the remaining addresses to continue.
+    #[endpoint(claimRewardsFor)]
+    fn claim_rewards_for(
+        &self,
+        #[var_args] addresses: VarArgs<Address>,
+    ) -> SCResult<MultiResult2<GlobalOpStatus, usize>> {
+        require!(self.pause().not_paused(), "contract paused");
+        feature_guard!(self.features_module(), b"claimRewards", true);
+
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "claim rewards is temporarily paused as checkpoint is reset"
+        );
+
+        self.reward_history().update_reward_history();
+
+        let addresses = addresses.into_vec();
+        for (num_processed, address) in addresses.iter().enumerate() {
+            if self.get_gas_left() < STOP_AT_GASLIMIT {
+                return Ok(MultiResult2::from((
+                    GlobalOpStatus::StoppedBeforeOutOfGas,
+                    num_processed,
+                )));
+            }
+
+            if let Some(user_id) = NonZeroUsize::new(self.user_data().get_user_id(address)) {
+                let user_data = self.load_updated_user_rewards(user_id);
+                sc_try!(self.claim_user_rewards(user_id, address, user_data));
+            }
+        }
+
+        Ok(MultiResult2::from((GlobalOpStatus::Done, addresses.len())))
     }
 
     fn send_rewards(&self, to: &Address, amount: &BigUint) {
@@ -244,11 +680,22 @@ pub trait RewardsModule {
         self.send_tx(to, amount, "delegation rewards claim");
 
         // increment globally sent funds
+        self.add_sent_rewards(amount);
+    }
+
+    fn add_sent_rewards(&self, amount: &BigUint) {
         let mut sent_rewards = self.get_sent_rewards();
         sent_rewards += amount;
         self.set_sent_rewards(&sent_rewards);
     }
 
+    fn record_user_claim(&self, user_id: NonZeroUsize, amount: &BigUint) {
+        let mut claimed = self.get_user_rew_claimed(user_id);
+        claimed += amount;
+        self.set_user_rew_claimed(user_id, &claimed);
+        self.set_user_last_claim_nonce(user_id, self.get_block_nonce());
+    }
+
     /// Loads the entire UserRewardData object from storage.
     fn load_user_reward_data(&self, user_id: NonZeroUsize) -> UserRewardData<BigUint> {
         let u_rew_checkp = self.get_user_rew_checkpoint(user_id);
@@ -261,12 +708,358 @@ pub trait RewardsModule {
 
     /// Saves a UserRewardData object to storage.
     fn store_user_reward_data(&self, user_id: NonZeroUsize, data: &UserRewardData<BigUint>) {
+        if user_id == OWNER_USER_ID {
+            self.record_owner_income(&data.reward_checkpoint);
+        }
+        if self.time_weighted_rewards().is_time_weighted_rewards() {
+            self.time_weighted_rewards()
+                .store_user_accumulators(user_id);
+        }
         self.set_user_rew_checkpoint(user_id, &data.reward_checkpoint);
         self.set_user_rew_unclaimed(user_id, &data.unclaimed_rewards);
     }
 
+    // SERVICE FEE DESTINATION
+
+    /// Address that receives the service fee.
+    /// If not set, the service fee is added to the owner rewards.
+    #[storage_get("service_fee_dest")]
+    fn get_service_fee_destination(&self) -> Address;
+
+    #[storage_set("service_fee_dest")]
+    fn set_service_fee_destination(&self, service_fee_destination: &Address);
+
+    #[storage_is_empty("service_fee_dest")]
+    fn is_empty_service_fee_destination(&self) -> bool;
+
+    #[storage_clear("service_fee_dest")]
+    fn clear_service_fee_destination(&self);
+
+    #[view(getServiceFeeDestination)]
+    fn get_service_fee_destination_endpoint(&self) -> OptionalResult<Address> {
+        if self.is_empty_service_fee_destination() {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(self.get_service_fee_destination())
+        }
+    }
+
+    /// Same as the user reward checkpoint, but for the service fee kept apart from the owner rewards.
+    #[storage_get("sf_rew_checkp")]
+    fn get_service_fee_rew_checkpoint(&self) -> BigUint;
+
+    #[storage_set("sf_rew_checkp")]
+    fn set_service_fee_rew_checkpoint(&self, sf_rew_checkpoint: &BigUint);
+
+    /// Service fee computed but not yet sent to the service fee destination.
+    #[storage_get("sf_rew_unclmd")]
+    fn get_service_fee_rew_unclaimed(&self) -> BigUint;
+
+    #[storage_set("sf_rew_unclmd")]
+    fn set_service_fee_rew_unclaimed(&self, sf_rew_unclaimed: &BigUint);
+
+    /// Does not update storage, only returns the service fee rewards object, after computing rewards.
+    /// Nothing accumulates while there is no service fee destination.
+    fn load_updated_service_fee_rewards(&self) -> UserRewardData<BigUint> {
+        let mut sf_data = UserRewardData {
+            reward_checkpoint: self.get_service_fee_rew_checkpoint(),
+            unclaimed_rewards: self.get_service_fee_rew_unclaimed(),
+        };
+        if self.is_empty_service_fee_destination() {
+            return sf_data;
+        }
+
+        let tot_cumul_rewards = self.get_total_cumulated_rewards();
+        for slice in self
+            .split_rewards_between(&sf_data.reward_checkpoint, &tot_cumul_rewards)
+            .iter()
+        {
+            sf_data.unclaimed_rewards += &slice.service_rewards;
+        }
+        sf_data.reward_checkpoint = tot_cumul_rewards;
+        sf_data
+    }
+
+    fn store_service_fee_reward_data(&self, sf_data: &UserRewardData<BigUint>) {
+        let old_checkpoint = self.get_service_fee_rew_checkpoint();
+        if sf_data.reward_checkpoint > old_checkpoint {
+            let mut destination_income = self.get_service_fee_destination_income();
+            for slice in self
+                .split_rewards_between(&old_checkpoint, &sf_data.reward_checkpoint)
+                .iter()
+            {
+                destination_income += &slice.service_rewards;
+            }
+            self.set_service_fee_destination_income(&destination_income);
+        }
+        self.set_service_fee_rew_checkpoint(&sf_data.reward_checkpoint);
+        self.set_service_fee_rew_unclaimed(&sf_data.unclaimed_rewards);
+    }
+
+    /// Brings the service fee kept apart up to date.
+    fn compute_service_fee_rewards(&self) -> UserRewardData<BigUint> {
+        let sf_data = self.load_updated_service_fee_rewards();
+        self.store_service_fee_reward_data(&sf_data);
+        sf_data
+    }
+
+    /// Sets where the service fee goes from now on. If no address is provided, it goes back to the owner.
+    /// Service fee that the previous destination did not claim yet is sent to it before switching.
+    #[endpoint(setServiceFeeDestination)]
+    fn set_service_fee_destination_endpoint(
+        &self,
+        #[var_args] destination: OptionalArg<Address>,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can set service fee destination");
+
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "global checkpoint is in progress"
+        );
+
+        // everything up to now is computed with the previous destination
+        self.compute_one_user_reward(OWNER_USER_ID);
+        let mut sf_data = self.compute_service_fee_rewards();
+
+        // settle the previous destination
+        if !self.is_empty_service_fee_destination() && sf_data.unclaimed_rewards > 0 {
+            let old_destination = self.get_service_fee_destination();
+            self.events()
+                .claim_rewards_event(&old_destination, &sf_data.unclaimed_rewards);
+
+            self.send_rewards(&old_destination, &sf_data.unclaimed_rewards);
+
+            sf_data.unclaimed_rewards = BigUint::zero();
+            self.set_service_fee_rew_unclaimed(&sf_data.unclaimed_rewards);
+        }
+
+        // nothing accumulated while there was no destination, so skip that interval
+        self.set_service_fee_rew_checkpoint(&self.get_total_cumulated_rewards());
+
+        match destination.into_option() {
+            Some(destination) => {
+                self.set_service_fee_destination(&destination);
+            }
+            None => {
+                self.clear_service_fee_destination();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Yields how much service fee the service fee destination can claim at the present time.
+    /// Does not update storage.
+    #[view(getClaimableServiceFee)]
+    fn get_claimable_service_fee(&self) -> BigUint {
+        self.load_updated_service_fee_rewards().unclaimed_rewards
+    }
+
+    /// Sends the service fee kept apart to the service fee destination.
+    #[endpoint(claimServiceFee)]
+    fn claim_service_fee(&self) -> SCResult<()> {
+        require!(self.pause().not_paused(), "contract paused");
+
+        let caller = self.get_caller();
+        require!(
+            !self.is_empty_service_fee_destination()
+                && caller == self.get_service_fee_destination(),
+            "only service fee destination can claim service fee"
+        );
+
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "claim rewards is temporarily paused as checkpoint is reset"
+        );
+
+        let mut sf_data = self.load_updated_service_fee_rewards();
+
+        if sf_data.unclaimed_rewards > 0 {
+            self.events()
+                .claim_rewards_event(&caller, &sf_data.unclaimed_rewards);
+
+            self.send_rewards(&caller, &sf_data.unclaimed_rewards);
+
+            sf_data.unclaimed_rewards = BigUint::zero();
+        }
+
+        self.store_service_fee_reward_data(&sf_data);
+
+        Ok(())
+    }
+
+    // INCOME BY SOURCE
+
+    #[storage_get("owner_income_sf")]
+    fn get_owner_service_fee_income(&self) -> BigUint;
+
+    #[storage_set("owner_income_sf")]
+    fn set_owner_service_fee_income(&self, income: &BigUint);
+
+    #[storage_get("owner_income_missing")]
+    fn get_owner_missing_stake_income(&self) -> BigUint;
+
+    #[storage_set("owner_income_missing")]
+    fn set_owner_missing_stake_income(&self, income: &BigUint);
+
+    #[storage_get("owner_income_stake")]
+    fn get_owner_stake_income(&self) -> BigUint;
+
+    #[storage_set("owner_income_stake")]
+    fn set_owner_stake_income(&self, income: &BigUint);
+
+    #[storage_get("owner_income_dust")]
+    fn get_owner_dust_income(&self) -> BigUint;
+
+    #[storage_set("owner_income_dust")]
+    fn set_owner_dust_income(&self, income: &BigUint);
+
+    /// Service fee that went to the service fee destination, across all destinations.
+    #[view(getServiceFeeDestinationIncome)]
+    #[storage_get("sf_dest_income")]
+    fn get_service_fee_destination_income(&self) -> BigUint;
+
+    #[storage_set("sf_dest_income")]
+    fn set_service_fee_destination_income(&self, income: &BigUint);
+
+    /// Rewards computed for the owner, by source:
+    /// - service fee, if there was no separate destination,
+    /// - rewards for the stake missing from the delegation cap,
+    /// - rewards for the owner's own Active stake,
+    /// - rounding remainders given to the owner.
+    /// Only includes rewards computed since this breakdown was introduced.
+    #[view(getOwnerIncomeBySource)]
+    fn get_owner_income_by_source(&self) -> MultiResult4<BigUint, BigUint, BigUint, BigUint> {
+        (
+            self.get_owner_service_fee_income(),
+            self.get_owner_missing_stake_income(),
+            self.get_owner_stake_income(),
+            self.get_owner_dust_income(),
+        )
+            .into()
+    }
+
+    /// Adds the owner rewards between the saved owner checkpoint and the new one to the income by source.
+    /// Must be called before saving the new checkpoint, with the same stake and settings used to compute the rewards.
+    /// With time-weighted rewards, the owner stake rewards accumulate by block and not by checkpoint,
+    /// so they are recorded even if the checkpoint did not move.
+    fn record_owner_income(&self, new_checkpoint: &BigUint) {
+        let time_weighted = self.time_weighted_rewards().is_time_weighted_rewards();
+        let mut service_fee_income = self.get_owner_service_fee_income();
+        let mut missing_stake_income = self.get_owner_missing_stake_income();
+        let mut stake_income = self.get_owner_stake_income();
+        let old_checkpoint = self.get_user_rew_checkpoint(OWNER_USER_ID);
+        if *new_checkpoint > old_checkpoint {
+            for slice in self
+                .split_rewards_between(&old_checkpoint, new_checkpoint)
+                .iter()
+            {
+                if self.is_empty_service_fee_destination() {
+                    service_fee_income += &slice.service_rewards;
+                }
+                if time_weighted {
+                    continue;
+                }
+                missing_stake_income += self.missing_stake_rewards(slice);
+                stake_income += self.active_stake_rewards(
+                    OWNER_USER_ID,
+                    &slice.total_delegators_rewards,
+                    &slice.delegation_cap,
+                );
+            }
+        }
+        if time_weighted {
+            let (owner_stake_rewards, owner_missing_stake_rewards) = self
+                .time_weighted_rewards()
+                .load_accumulated_user_rewards(OWNER_USER_ID);
+            stake_income += owner_stake_rewards;
+            missing_stake_income += owner_missing_stake_rewards;
+        }
+        self.set_owner_service_fee_income(&service_fee_income);
+        self.set_owner_missing_stake_income(&missing_stake_income);
+        self.set_owner_stake_income(&stake_income);
+    }
+
+    /// Stake sent to the auction SC since the contract creation, including top-ups.
+    #[view(getTotalSentToAuction)]
+    #[storage_get("sent_to_auction")]
+    fn get_total_sent_to_auction(&self) -> BigUint;
+
+    #[storage_set("sent_to_auction")]
+    fn set_total_sent_to_auction(&self, sent_to_auction: &BigUint);
+
+    /// Funds received back from the auction SC since the contract creation:
+    /// unbonded stake, claimed unused funds and stake from failed calls.
+    #[view(getTotalReturnedFromAuction)]
+    #[storage_get("returned_from_auction")]
+    fn get_total_returned_from_auction(&self) -> BigUint;
+
+    #[storage_set("returned_from_auction")]
+    fn set_total_returned_from_auction(&self, returned_from_auction: &BigUint);
+
+    /// Stake that is currently in the auction SC, as far as the contract knows.
+    #[view(getTotalStakeInAuction)]
+    fn get_total_stake_in_auction(&self) -> BigUint {
+        let sent = self.get_total_sent_to_auction();
+        let returned = self.get_total_returned_from_auction();
+        if sent > returned {
+            sent - returned
+        } else {
+            BigUint::zero()
+        }
+    }
+
+    /// Stake in the auction SC that is not attributed to any node,
+    /// i.e. stake and top-ups that the auction SC accepted but that failed for some of the nodes.
+    /// It is sent back when claiming unused funds.
+    #[view(getTotalUnusedInAuction)]
+    #[storage_get("unused_in_auction")]
+    fn get_total_unused_in_auction(&self) -> BigUint;
+
+    #[storage_set("unused_in_auction")]
+    fn set_total_unused_in_auction(&self, unused_in_auction: &BigUint);
+
+    fn add_unused_in_auction(&self, amount: &BigUint) {
+        let mut unused_in_auction = self.get_total_unused_in_auction();
+        unused_in_auction += amount;
+        self.set_total_unused_in_auction(&unused_in_auction);
+    }
+
+    fn add_sent_to_auction(&self, amount: &BigUint) {
+        let mut sent_to_auction = self.get_total_sent_to_auction();
+        sent_to_auction += amount;
+        self.set_total_sent_to_auction(&sent_to_auction);
+    }
+
+    fn add_returned_from_auction(&self, amount: &BigUint) {
+        let mut returned_from_auction = self.get_total_returned_from_auction();
+        returned_from_auction += amount;
+        self.set_total_returned_from_auction(&returned_from_auction);
+    }
+
+    /// Active and unstaked stake that is not in the auction SC, so it is available for staking nodes.
     #[view(getTotalUnProtected)]
     fn total_unprotected(&self) -> BigUint {
+        let total_active = self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active);
+        let total_unstaked = self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::UnStaked);
+        let total_stake_in_auction = self.get_total_stake_in_auction();
+
+        let total_stake = total_active + total_unstaked;
+        if total_stake > total_stake_in_auction {
+            total_stake - total_stake_in_auction
+        } else {
+            BigUint::zero()
+        }
+    }
+
+    /// Same as `getTotalUnProtected`, but inferred from the contract balance.
+    /// The two should only differ if funds reached the contract without passing through its endpoints.
+    #[view(getTotalUnProtectedFromBalance)]
+    fn total_unprotected_from_balance(&self) -> BigUint {
         let sent_rewards = self.get_sent_rewards();
         let total_rewards = self.get_total_cumulated_rewards();
         let total_waiting = self
@@ -286,4 +1079,38 @@ pub trait RewardsModule {
         unprotected -= total_withdraw;
         unprotected
     }
+
+    /// Funds sent back by the auction SC reach the contract balance without passing through its endpoints.
+    /// They are measured as the part of the balance that none of the counters accounts for,
+    /// i.e. what `getTotalUnProtectedFromBalance` has on top of `getTotalUnProtected`.
+    /// Books them as returned from the auction SC and yields the amount booked.
+    fn book_funds_returned_from_auction(&self) -> BigUint {
+        let mut accounted_for = self.get_total_cumulated_rewards();
+        accounted_for += self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Waiting);
+        accounted_for += self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active);
+        accounted_for += self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::UnStaked);
+        accounted_for += self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::DeferredPayment);
+        accounted_for += self
+            .fund_view_module()
+            .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::WithdrawOnly);
+        accounted_for += self.get_total_returned_from_auction();
+
+        let balance_and_sent =
+            self.get_sc_balance() + self.get_sent_rewards() + self.get_total_sent_to_auction();
+        if balance_and_sent > accounted_for {
+            let returned = balance_and_sent - accounted_for;
+            self.add_returned_from_auction(&returned);
+            returned
+        } else {
+            BigUint::zero()
+        }
+    }
 }
@@ -1,6 +1,9 @@
 use super::node_storage::node_config::*;
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
 use super::user_fund_storage::fund_transf_module::*;
 use super::user_fund_storage::user_data::*;
+use crate::events::*;
 use crate::reset_checkpoint_types::*;
 use crate::reset_checkpoints::*;
 use crate::rewards::*;
@@ -22,6 +25,12 @@ pub static OWNER_USER_ID: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1)
 ///
 #[elrond_wasm_derive::module(SettingsModuleImpl)]
 pub trait SettingsModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(EventsModuleImpl)]
+    fn events(&self) -> EventsModuleImpl<T, BigInt, BigUint>;
+
     #[module(UserDataModuleImpl)]
     fn user_data(&self) -> UserDataModuleImpl<T, BigInt, BigUint>;
 
@@ -57,15 +66,40 @@ pub trait SettingsModule {
 
     /// The stake per node can be changed by the owner.
     /// It does not get set in the contructor, so the owner has to manually set it after the contract is deployed.
+    /// If a notice period is configured, increases must be scheduled with `scheduleServiceFeeChange` instead.
     #[endpoint(setServiceFee)]
     fn set_service_fee_endpoint(&self, service_fee_per_10000: usize) -> SCResult<GlobalOpStatus> {
-        only_owner!(self, "only owner can change service fee");
+        only_delegation_owner!(self, "only owner can change service fee");
+
+        sc_try!(self.validate_service_fee_change(service_fee_per_10000));
+
+        require!(
+            self.get_n_blocks_before_service_fee_change() == 0
+                || BigUint::from(service_fee_per_10000) <= self.get_service_fee(),
+            "service fee increase must be scheduled"
+        );
 
+        self.change_service_fee(service_fee_per_10000)
+    }
+
+    fn validate_service_fee_change(&self, service_fee_per_10000: usize) -> SCResult<()> {
         require!(
             service_fee_per_10000 <= PERCENTAGE_DENOMINATOR,
             "service fee out of range"
         );
 
+        if let Some(max_increase) = self.get_max_service_fee_increase() {
+            let max_service_fee = self.get_service_fee() + BigUint::from(max_increase);
+            require!(
+                BigUint::from(service_fee_per_10000) <= max_service_fee,
+                "service fee increase too high"
+            );
+        }
+
+        Ok(())
+    }
+
+    fn change_service_fee(&self, service_fee_per_10000: usize) -> SCResult<GlobalOpStatus> {
         require!(
             !self.reset_checkpoints().is_global_op_in_progress(),
             "global checkpoint is in progress"
@@ -76,24 +110,597 @@ pub trait SettingsModule {
             return Ok(GlobalOpStatus::Done);
         }
 
-        if self.is_bootstrap_mode() {
-            // no rewards to compute
-            // change service fee directly
-            self.set_service_fee(new_service_fee);
-            Ok(GlobalOpStatus::Done)
+        // rewards received so far keep being split with the old service fee
+        self.rewards().end_reward_regime();
+        self.set_service_fee(new_service_fee);
+        Ok(GlobalOpStatus::Done)
+    }
+
+    #[storage_get("n_blocks_before_fee_change")]
+    fn get_stored_n_blocks_before_service_fee_change(&self) -> u64;
+
+    #[storage_set("n_blocks_before_fee_change")]
+    fn set_n_blocks_before_service_fee_change(&self, n_blocks_before_fee_change: u64);
+
+    #[storage_get("sched_n_blocks_before_fee_change")]
+    fn get_scheduled_n_blocks_before_service_fee_change(&self) -> u64;
+
+    #[storage_set("sched_n_blocks_before_fee_change")]
+    fn set_scheduled_n_blocks_before_service_fee_change(&self, n_blocks_before_fee_change: u64);
+
+    #[storage_clear("sched_n_blocks_before_fee_change")]
+    fn clear_scheduled_n_blocks_before_service_fee_change(&self);
+
+    #[storage_get("sched_n_blocks_before_fee_change_nonce")]
+    fn get_scheduled_n_blocks_before_service_fee_change_nonce(&self) -> u64;
+
+    #[storage_set("sched_n_blocks_before_fee_change_nonce")]
+    fn set_scheduled_n_blocks_before_service_fee_change_nonce(&self, activation_nonce: u64);
+
+    #[storage_is_empty("sched_n_blocks_before_fee_change_nonce")]
+    fn is_empty_scheduled_n_blocks_before_service_fee_change_nonce(&self) -> bool;
+
+    #[storage_clear("sched_n_blocks_before_fee_change_nonce")]
+    fn clear_scheduled_n_blocks_before_service_fee_change_nonce(&self);
+
+    /// Minimum number of blocks between scheduling a service fee increase and it taking effect,
+    /// so delegators have time to react.
+    /// Includes a scheduled lowering of the notice period, once it is active.
+    #[view(getNumBlocksBeforeServiceFeeChange)]
+    fn get_n_blocks_before_service_fee_change(&self) -> u64 {
+        if !self.is_empty_scheduled_n_blocks_before_service_fee_change_nonce()
+            && self.get_block_nonce()
+                >= self.get_scheduled_n_blocks_before_service_fee_change_nonce()
+        {
+            self.get_scheduled_n_blocks_before_service_fee_change()
+        } else {
+            self.get_stored_n_blocks_before_service_fee_change()
+        }
+    }
+
+    /// Yields the lowered notice period and the block nonce from which it applies.
+    /// Yields nothing if no lowering is scheduled, or if it is already active.
+    #[view(getScheduledNumBlocksBeforeServiceFeeChange)]
+    fn get_scheduled_n_blocks_before_service_fee_change_endpoint(
+        &self,
+    ) -> OptionalResult<MultiResult2<u64, u64>> {
+        let activation_nonce = self.get_scheduled_n_blocks_before_service_fee_change_nonce();
+        if self.is_empty_scheduled_n_blocks_before_service_fee_change_nonce()
+            || self.get_block_nonce() >= activation_nonce
+        {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(MultiResult2::from((
+                self.get_scheduled_n_blocks_before_service_fee_change(),
+                activation_nonce,
+            )))
+        }
+    }
+
+    /// Raising the notice period applies immediately.
+    /// Lowering it would allow fee increases with less notice, so it only applies after the current notice period.
+    /// Replaces any previously scheduled lowering.
+    /// Already scheduled service fee changes keep their activation nonce.
+    #[endpoint(setNumBlocksBeforeServiceFeeChange)]
+    fn set_n_blocks_before_service_fee_change_endpoint(
+        &self,
+        n_blocks_before_fee_change: u64,
+    ) -> SCResult<()> {
+        only_delegation_owner!(
+            self,
+            "only owner can set num blocks before service fee change"
+        );
+
+        let current_n_blocks = self.get_n_blocks_before_service_fee_change();
+        self.clear_scheduled_n_blocks_before_service_fee_change();
+        self.clear_scheduled_n_blocks_before_service_fee_change_nonce();
+        if n_blocks_before_fee_change >= current_n_blocks {
+            self.set_n_blocks_before_service_fee_change(n_blocks_before_fee_change);
+        } else {
+            self.set_n_blocks_before_service_fee_change(current_n_blocks);
+            self.set_scheduled_n_blocks_before_service_fee_change(n_blocks_before_fee_change);
+            self.set_scheduled_n_blocks_before_service_fee_change_nonce(
+                self.get_block_nonce() + current_n_blocks,
+            );
+        }
+        Ok(())
+    }
+
+    #[storage_get("max_service_fee_increase")]
+    fn get_stored_max_service_fee_increase(&self) -> usize;
+
+    #[storage_set("max_service_fee_increase")]
+    fn set_max_service_fee_increase(&self, max_service_fee_increase: usize);
+
+    #[storage_is_empty("max_service_fee_increase")]
+    fn is_empty_max_service_fee_increase(&self) -> bool;
+
+    #[storage_clear("max_service_fee_increase")]
+    fn clear_max_service_fee_increase(&self);
+
+    /// A scheduled removal of the limit is stored as an increase of 100%, which is the same.
+    #[storage_get("sched_max_service_fee_increase")]
+    fn get_scheduled_max_service_fee_increase(&self) -> usize;
+
+    #[storage_set("sched_max_service_fee_increase")]
+    fn set_scheduled_max_service_fee_increase(&self, max_service_fee_increase: usize);
+
+    #[storage_clear("sched_max_service_fee_increase")]
+    fn clear_scheduled_max_service_fee_increase(&self);
+
+    #[storage_get("sched_max_service_fee_increase_nonce")]
+    fn get_scheduled_max_service_fee_increase_nonce(&self) -> u64;
+
+    #[storage_set("sched_max_service_fee_increase_nonce")]
+    fn set_scheduled_max_service_fee_increase_nonce(&self, activation_nonce: u64);
+
+    #[storage_is_empty("sched_max_service_fee_increase_nonce")]
+    fn is_empty_scheduled_max_service_fee_increase_nonce(&self) -> bool;
+
+    #[storage_clear("sched_max_service_fee_increase_nonce")]
+    fn clear_scheduled_max_service_fee_increase_nonce(&self);
+
+    /// How much the service fee can rise with one change. 10000 = 100%.
+    /// None means there is no limit.
+    /// Includes a scheduled loosening of the limit, once it is active.
+    fn get_max_service_fee_increase(&self) -> Option<usize> {
+        if !self.is_empty_scheduled_max_service_fee_increase_nonce()
+            && self.get_block_nonce() >= self.get_scheduled_max_service_fee_increase_nonce()
+        {
+            let max_increase = self.get_scheduled_max_service_fee_increase();
+            if max_increase >= PERCENTAGE_DENOMINATOR {
+                None
+            } else {
+                Some(max_increase)
+            }
+        } else if self.is_empty_max_service_fee_increase() {
+            None
+        } else {
+            Some(self.get_stored_max_service_fee_increase())
+        }
+    }
+
+    #[view(getMaxServiceFeeIncrease)]
+    fn get_max_service_fee_increase_endpoint(&self) -> OptionalResult<usize> {
+        self.get_max_service_fee_increase().into()
+    }
+
+    /// Yields the loosened limit and the block nonce from which it applies. 10000 means no limit.
+    /// Yields nothing if no loosening is scheduled, or if it is already active.
+    #[view(getScheduledMaxServiceFeeIncrease)]
+    fn get_scheduled_max_service_fee_increase_endpoint(
+        &self,
+    ) -> OptionalResult<MultiResult2<usize, u64>> {
+        let activation_nonce = self.get_scheduled_max_service_fee_increase_nonce();
+        if self.is_empty_scheduled_max_service_fee_increase_nonce()
+            || self.get_block_nonce() >= activation_nonce
+        {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(MultiResult2::from((
+                self.get_scheduled_max_service_fee_increase(),
+                activation_nonce,
+            )))
+        }
+    }
+
+    /// Removes the limit if no argument is provided. A limit of 100% is the same as no limit.
+    /// Lowering the limit applies immediately.
+    /// Raising or removing it would allow bigger fee increases, so it only applies after the notice period.
+    /// Replaces any previously scheduled loosening.
+    #[endpoint(setMaxServiceFeeIncrease)]
+    fn set_max_service_fee_increase_endpoint(
+        &self,
+        #[var_args] max_service_fee_increase: OptionalArg<usize>,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can set max service fee increase");
+
+        let new_max_increase = match max_service_fee_increase.into_option() {
+            Some(max_increase) => {
+                require!(
+                    max_increase <= PERCENTAGE_DENOMINATOR,
+                    "max service fee increase out of range"
+                );
+                max_increase
+            }
+            None => PERCENTAGE_DENOMINATOR,
+        };
+
+        let current_max_increase = self
+            .get_max_service_fee_increase()
+            .unwrap_or(PERCENTAGE_DENOMINATOR);
+        self.clear_scheduled_max_service_fee_increase();
+        self.clear_scheduled_max_service_fee_increase_nonce();
+        let n_blocks_before_fee_change = self.get_n_blocks_before_service_fee_change();
+        if new_max_increase <= current_max_increase || n_blocks_before_fee_change == 0 {
+            self.store_max_service_fee_increase(new_max_increase);
+        } else {
+            self.store_max_service_fee_increase(current_max_increase);
+            self.set_scheduled_max_service_fee_increase(new_max_increase);
+            self.set_scheduled_max_service_fee_increase_nonce(
+                self.get_block_nonce() + n_blocks_before_fee_change,
+            );
+        }
+        Ok(())
+    }
+
+    fn store_max_service_fee_increase(&self, max_service_fee_increase: usize) {
+        if max_service_fee_increase >= PERCENTAGE_DENOMINATOR {
+            self.clear_max_service_fee_increase();
+        } else {
+            self.set_max_service_fee_increase(max_service_fee_increase);
+        }
+    }
+
+    #[storage_get("scheduled_service_fee")]
+    fn get_scheduled_service_fee(&self) -> usize;
+
+    #[storage_set("scheduled_service_fee")]
+    fn set_scheduled_service_fee(&self, service_fee_per_10000: usize);
+
+    #[storage_get("scheduled_service_fee_nonce")]
+    fn get_scheduled_service_fee_nonce(&self) -> u64;
+
+    #[storage_set("scheduled_service_fee_nonce")]
+    fn set_scheduled_service_fee_nonce(&self, activation_nonce: u64);
+
+    #[storage_is_empty("scheduled_service_fee_nonce")]
+    fn is_empty_scheduled_service_fee_nonce(&self) -> bool;
+
+    #[storage_clear("scheduled_service_fee")]
+    fn clear_scheduled_service_fee(&self);
+
+    #[storage_clear("scheduled_service_fee_nonce")]
+    fn clear_scheduled_service_fee_nonce(&self);
+
+    fn clear_scheduled_service_fee_change(&self) {
+        self.clear_scheduled_service_fee();
+        self.clear_scheduled_service_fee_nonce();
+    }
+
+    /// Yields the scheduled service fee and the block nonce from which it can be applied.
+    /// Yields nothing if no change is scheduled.
+    #[view(getScheduledServiceFeeChange)]
+    fn get_scheduled_service_fee_change(&self) -> OptionalResult<MultiResult2<usize, u64>> {
+        if self.is_empty_scheduled_service_fee_nonce() {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(MultiResult2::from((
+                self.get_scheduled_service_fee(),
+                self.get_scheduled_service_fee_nonce(),
+            )))
+        }
+    }
+
+    /// Owner announces a service fee change, that can only be applied after the notice period.
+    /// Replaces any previously scheduled change.
+    #[endpoint(scheduleServiceFeeChange)]
+    fn schedule_service_fee_change(&self, service_fee_per_10000: usize) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change service fee");
+
+        sc_try!(self.validate_service_fee_change(service_fee_per_10000));
+
+        // nonce 0 would mean nothing scheduled, so start from 1
+        let activation_nonce = core::cmp::max(
+            self.get_block_nonce() + self.get_n_blocks_before_service_fee_change(),
+            1,
+        );
+        self.set_scheduled_service_fee(service_fee_per_10000);
+        self.set_scheduled_service_fee_nonce(activation_nonce);
+
+        self.events()
+            .service_fee_change_scheduled_event(&(service_fee_per_10000, activation_nonce));
+        Ok(())
+    }
+
+    #[endpoint(cancelServiceFeeChange)]
+    fn cancel_service_fee_change(&self) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change service fee");
+        require!(
+            !self.is_empty_scheduled_service_fee_nonce(),
+            "no service fee change scheduled"
+        );
+        self.clear_scheduled_service_fee_change();
+        Ok(())
+    }
+
+    /// Applies the scheduled service fee change, once the notice period is over.
+    /// The limit of the service fee increase is checked again, against the service fee at this time.
+    /// Anyone can call it.
+    #[endpoint(applyServiceFeeChange)]
+    fn apply_service_fee_change(&self) -> SCResult<GlobalOpStatus> {
+        require!(
+            !self.is_empty_scheduled_service_fee_nonce(),
+            "no service fee change scheduled"
+        );
+        require!(
+            self.get_block_nonce() >= self.get_scheduled_service_fee_nonce(),
+            "service fee change not yet active"
+        );
+
+        let service_fee_per_10000 = self.get_scheduled_service_fee();
+        sc_try!(self.validate_service_fee_change(service_fee_per_10000));
+
+        self.clear_scheduled_service_fee_change();
+        self.change_service_fee(service_fee_per_10000)
+    }
+
+    /// Sorted by minimum delegation cap, strictly increasing.
+    #[storage_get("service_fee_tiers")]
+    fn get_service_fee_tiers(&self) -> Vec<ServiceFeeTier<BigUint>>;
+
+    #[storage_set("service_fee_tiers")]
+    fn set_service_fee_tiers(&self, service_fee_tiers: &[ServiceFeeTier<BigUint>]);
+
+    /// Yields pairs of (minimum total delegation cap, service fee), in increasing order of the cap.
+    #[view(getServiceFeeTiers)]
+    fn get_service_fee_tiers_endpoint(&self) -> MultiResultVec<MultiResult2<BigUint, BigUint>> {
+        self.get_service_fee_tiers()
+            .into_iter()
+            .map(|tier| MultiResult2::from((tier.min_delegation_cap, tier.service_fee)))
+            .collect()
+    }
+
+    /// The service fee actually applied to incoming rewards.
+    /// It is the fee of the highest tier reached by the total delegation cap,
+    /// but never more than the base service fee.
+    /// The total delegation cap is also the base for computing rewards,
+    /// and all rewards are computed before it changes,
+    /// so rewards always get split using the tier in effect when they arrived.
+    #[view(getEffectiveServiceFee)]
+    fn get_effective_service_fee(&self) -> BigUint {
+        self.compute_effective_service_fee(
+            self.get_service_fee(),
+            self.get_service_fee_tiers().as_slice(),
+            &self.get_total_delegation_cap(),
+        )
+    }
+
+    fn compute_effective_service_fee(
+        &self,
+        service_fee: BigUint,
+        service_fee_tiers: &[ServiceFeeTier<BigUint>],
+        total_delegation_cap: &BigUint,
+    ) -> BigUint {
+        let tier_service_fee = service_fee_tiers
+            .iter()
+            .take_while(|tier| &tier.min_delegation_cap <= total_delegation_cap)
+            .last()
+            .map(|tier| &tier.service_fee);
+        match tier_service_fee {
+            Some(tier_service_fee) if tier_service_fee < &service_fee => tier_service_fee.clone(),
+            _ => service_fee,
+        }
+    }
+
+    /// The biggest rise of the effective service fee, over all total delegation caps,
+    /// if the current tiers were replaced with the new ones.
+    /// The effective fee only changes at tier boundaries, so it is enough to check those.
+    fn service_fee_tiers_increase(
+        &self,
+        new_service_fee_tiers: &[ServiceFeeTier<BigUint>],
+    ) -> BigUint {
+        let service_fee = self.get_service_fee();
+        let old_service_fee_tiers = self.get_service_fee_tiers();
+        let mut max_increase = BigUint::zero();
+        let tier_boundaries = old_service_fee_tiers
+            .iter()
+            .chain(new_service_fee_tiers.iter())
+            .map(|tier| tier.min_delegation_cap.clone());
+        for total_delegation_cap in core::iter::once(BigUint::zero()).chain(tier_boundaries) {
+            let old_fee = self.compute_effective_service_fee(
+                service_fee.clone(),
+                old_service_fee_tiers.as_slice(),
+                &total_delegation_cap,
+            );
+            let new_fee = self.compute_effective_service_fee(
+                service_fee.clone(),
+                new_service_fee_tiers,
+                &total_delegation_cap,
+            );
+            if new_fee > old_fee {
+                let increase = new_fee - old_fee;
+                if increase > max_increase {
+                    max_increase = increase;
+                }
+            }
+        }
+        max_increase
+    }
+
+    /// Checks the tiers and yields the biggest rise of the effective service fee they would cause.
+    fn validate_service_fee_tiers_change(
+        &self,
+        new_service_fee_tiers: &[ServiceFeeTier<BigUint>],
+    ) -> SCResult<BigUint> {
+        let mut prev_tier: Option<&ServiceFeeTier<BigUint>> = None;
+        for tier in new_service_fee_tiers.iter() {
+            require!(
+                tier.service_fee <= BigUint::from(PERCENTAGE_DENOMINATOR),
+                "service fee out of range"
+            );
+            if let Some(prev_tier) = prev_tier {
+                require!(
+                    tier.min_delegation_cap > prev_tier.min_delegation_cap,
+                    "service fee tiers must have increasing delegation caps"
+                );
+            }
+            prev_tier = Some(tier);
+        }
+
+        let increase = self.service_fee_tiers_increase(new_service_fee_tiers);
+        if let Some(max_increase) = self.get_max_service_fee_increase() {
+            require!(
+                increase <= BigUint::from(max_increase),
+                "service fee increase too high"
+            );
+        }
+
+        Ok(increase)
+    }
+
+    fn service_fee_tiers_from_args(
+        &self,
+        tier_args: VarArgs<MultiArg2<BigUint, usize>>,
+    ) -> Vec<ServiceFeeTier<BigUint>> {
+        tier_args
+            .into_vec()
+            .into_iter()
+            .map(|tier_arg| {
+                let (min_delegation_cap, service_fee_per_10000) = tier_arg.into_tuple();
+                ServiceFeeTier {
+                    min_delegation_cap,
+                    service_fee: BigUint::from(service_fee_per_10000),
+                }
+            })
+            .collect()
+    }
+
+    /// Replaces the service fee tiers.
+    /// Expects pairs of (minimum total delegation cap, service fee), with strictly increasing caps.
+    /// Tiers can only lower the fee below the base service fee.
+    /// Changes that raise the effective fee at any total delegation cap are subject to the max service fee increase,
+    /// and if a notice period is configured, they must be scheduled with `scheduleServiceFeeTiersChange` instead.
+    /// Removes all tiers if no arguments are provided.
+    #[endpoint(setServiceFeeTiers)]
+    fn set_service_fee_tiers_endpoint(
+        &self,
+        #[var_args] tier_args: VarArgs<MultiArg2<BigUint, usize>>,
+    ) -> SCResult<GlobalOpStatus> {
+        only_delegation_owner!(self, "only owner can change service fee");
+
+        let new_service_fee_tiers = self.service_fee_tiers_from_args(tier_args);
+        let increase =
+            sc_try!(self.validate_service_fee_tiers_change(new_service_fee_tiers.as_slice()));
+
+        require!(
+            self.get_n_blocks_before_service_fee_change() == 0 || increase == 0,
+            "service fee increase must be scheduled"
+        );
+
+        self.change_service_fee_tiers(new_service_fee_tiers.as_slice())
+    }
+
+    fn change_service_fee_tiers(
+        &self,
+        new_service_fee_tiers: &[ServiceFeeTier<BigUint>],
+    ) -> SCResult<GlobalOpStatus> {
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "global checkpoint is in progress"
+        );
+
+        // rewards received so far keep being split with the old tiers
+        self.rewards().end_reward_regime();
+        self.set_service_fee_tiers(new_service_fee_tiers);
+        Ok(GlobalOpStatus::Done)
+    }
+
+    #[storage_get("scheduled_service_fee_tiers")]
+    fn get_scheduled_service_fee_tiers(&self) -> Vec<ServiceFeeTier<BigUint>>;
+
+    #[storage_set("scheduled_service_fee_tiers")]
+    fn set_scheduled_service_fee_tiers(&self, service_fee_tiers: &[ServiceFeeTier<BigUint>]);
+
+    #[storage_clear("scheduled_service_fee_tiers")]
+    fn clear_scheduled_service_fee_tiers(&self);
+
+    #[storage_get("scheduled_service_fee_tiers_nonce")]
+    fn get_scheduled_service_fee_tiers_nonce(&self) -> u64;
+
+    #[storage_set("scheduled_service_fee_tiers_nonce")]
+    fn set_scheduled_service_fee_tiers_nonce(&self, activation_nonce: u64);
+
+    #[storage_is_empty("scheduled_service_fee_tiers_nonce")]
+    fn is_empty_scheduled_service_fee_tiers_nonce(&self) -> bool;
+
+    #[storage_clear("scheduled_service_fee_tiers_nonce")]
+    fn clear_scheduled_service_fee_tiers_nonce(&self);
+
+    fn clear_scheduled_service_fee_tiers_change(&self) {
+        self.clear_scheduled_service_fee_tiers();
+        self.clear_scheduled_service_fee_tiers_nonce();
+    }
+
+    /// Yields the block nonce from which the scheduled service fee tiers can be applied.
+    /// Yields nothing if no change is scheduled.
+    #[view(getScheduledServiceFeeTiersChangeNonce)]
+    fn get_scheduled_service_fee_tiers_change_nonce(&self) -> OptionalResult<u64> {
+        if self.is_empty_scheduled_service_fee_tiers_nonce() {
+            OptionalResult::None
         } else {
-            // start compute all rewards
-            self.reset_checkpoints().continue_global_operation(Box::new(
-                GlobalOpCheckpoint::ChangeServiceFee {
-                    new_service_fee,
-                    compute_rewards_data: ComputeAllRewardsData::new(
-                        self.rewards().get_total_cumulated_rewards(),
-                    ),
-                },
-            ))
+            OptionalResult::Some(self.get_scheduled_service_fee_tiers_nonce())
         }
     }
 
+    /// Yields the scheduled pairs of (minimum total delegation cap, service fee).
+    #[view(getScheduledServiceFeeTiers)]
+    fn get_scheduled_service_fee_tiers_endpoint(
+        &self,
+    ) -> MultiResultVec<MultiResult2<BigUint, BigUint>> {
+        self.get_scheduled_service_fee_tiers()
+            .into_iter()
+            .map(|tier| MultiResult2::from((tier.min_delegation_cap, tier.service_fee)))
+            .collect()
+    }
+
+    /// Owner announces new service fee tiers, that can only be applied after the notice period.
+    /// Replaces any previously scheduled tiers.
+    #[endpoint(scheduleServiceFeeTiersChange)]
+    fn schedule_service_fee_tiers_change(
+        &self,
+        #[var_args] tier_args: VarArgs<MultiArg2<BigUint, usize>>,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change service fee");
+
+        let new_service_fee_tiers = self.service_fee_tiers_from_args(tier_args);
+        sc_try!(self.validate_service_fee_tiers_change(new_service_fee_tiers.as_slice()));
+
+        // nonce 0 would mean nothing scheduled, so start from 1
+        let activation_nonce = core::cmp::max(
+            self.get_block_nonce() + self.get_n_blocks_before_service_fee_change(),
+            1,
+        );
+        self.set_scheduled_service_fee_tiers(new_service_fee_tiers.as_slice());
+        self.set_scheduled_service_fee_tiers_nonce(activation_nonce);
+
+        self.events()
+            .service_fee_tiers_change_scheduled_event(&activation_nonce);
+        Ok(())
+    }
+
+    #[endpoint(cancelServiceFeeTiersChange)]
+    fn cancel_service_fee_tiers_change(&self) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change service fee");
+        require!(
+            !self.is_empty_scheduled_service_fee_tiers_nonce(),
+            "no service fee tiers change scheduled"
+        );
+        self.clear_scheduled_service_fee_tiers_change();
+        Ok(())
+    }
+
+    /// Applies the scheduled service fee tiers, once the notice period is over.
+    /// The limit of the service fee increase is checked again, against the fees at this time.
+    /// Anyone can call it.
+    #[endpoint(applyServiceFeeTiersChange)]
+    fn apply_service_fee_tiers_change(&self) -> SCResult<GlobalOpStatus> {
+        require!(
+            !self.is_empty_scheduled_service_fee_tiers_nonce(),
+            "no service fee tiers change scheduled"
+        );
+        require!(
+            self.get_block_nonce() >= self.get_scheduled_service_fee_tiers_nonce(),
+            "service fee tiers change not yet active"
+        );
+
+        let new_service_fee_tiers = self.get_scheduled_service_fee_tiers();
+        sc_try!(self.validate_service_fee_tiers_change(new_service_fee_tiers.as_slice()));
+
+        self.clear_scheduled_service_fee_tiers_change();
+        self.change_service_fee_tiers(new_service_fee_tiers.as_slice())
+    }
+
     #[view(getTotalDelegationCap)]
     #[storage_get("total_delegation_cap")]
     fn get_total_delegation_cap(&self) -> BigUint;
@@ -140,11 +747,38 @@ pub trait SettingsModule {
 
     #[endpoint(setNumBlocksBeforeUnbond)]
     fn set_n_blocks_before_unbond_endpoint(&self, n_blocks_before_unbond: u64) -> SCResult<()> {
-        only_owner!(self, "only owner can set num blocks before unbond");
+        only_delegation_owner!(self, "only owner can set num blocks before unbond");
         self.set_n_blocks_before_unbond(n_blocks_before_unbond);
         Ok(())
     }
 
+    /// Minimum number of blocks a node needs to wait for an auction SC callback
+    /// before the owner can force it back to its previous state.
+    /// Zero means not configured, in which case pending nodes cannot be recovered at all.
+    #[view(getNumBlocksBeforePendingRecovery)]
+    #[storage_get("n_blocks_before_pending_recovery")]
+    fn get_n_blocks_before_pending_recovery(&self) -> u64;
+
+    #[storage_set("n_blocks_before_pending_recovery")]
+    fn set_n_blocks_before_pending_recovery(&self, n_blocks_before_pending_recovery: u64);
+
+    #[endpoint(setNumBlocksBeforePendingRecovery)]
+    fn set_n_blocks_before_pending_recovery_endpoint(
+        &self,
+        n_blocks_before_pending_recovery: u64,
+    ) -> SCResult<()> {
+        only_delegation_owner!(
+            self,
+            "only owner can set num blocks before pending recovery"
+        );
+        require!(
+            n_blocks_before_pending_recovery > 0,
+            "num blocks before pending recovery must be positive"
+        );
+        self.set_n_blocks_before_pending_recovery(n_blocks_before_pending_recovery);
+        Ok(())
+    }
+
     /// Delegators are not allowed make transactions with less then this amount of stake (of any type).
     /// Zero means disabled.
     #[view(getMinimumStake)]
@@ -156,7 +790,7 @@ pub trait SettingsModule {
 
     #[endpoint(setMinimumStake)]
     fn set_minimum_stake_endpoint(&self, minimum_stake: BigUint) -> SCResult<()> {
-        only_owner!(self, "only owner can set minimum stake");
+        only_delegation_owner!(self, "only owner can set minimum stake");
         self.set_minimum_stake(&minimum_stake);
         Ok(())
     }
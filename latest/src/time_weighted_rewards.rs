@@ -0,0 +1,505 @@
+use super::node_storage::only_delegation_owner;
+use super::node_storage::operators::*;
+use super::settings::*;
+use super::user_fund_storage::fund_view_module::*;
+use super::user_fund_storage::types::*;
+use crate::reset_checkpoints::*;
+use crate::rewards::*;
+use core::num::NonZeroUsize;
+
+imports!();
+
+/// Rewards per stake are scaled by this much, so small rewards are not lost to rounding.
+pub const REWARD_PER_STAKE_PRECISION: u64 = 1_000_000_000_000_000_000;
+
+/// A reward period ends when new rewards are observed in the contract,
+/// i.e. the next time the time-weighted accounting is brought up to date.
+#[derive(Clone)]
+pub struct RewardPeriod<BigUint> {
+    /// Delegator rewards of the period per unit of stake and per block, times REWARD_PER_STAKE_PRECISION.
+    pub reward_per_stake_block: BigUint,
+
+    /// Block nonce when the period ended.
+    pub end_block: u64,
+
+    /// Delegator rewards per unit of stake that was active since the first period until the end of this one,
+    /// times REWARD_PER_STAKE_PRECISION.
+    pub reward_per_stake: BigUint,
+}
+
+/// State of the time-weighted accounting, brought up to the current block.
+pub struct RewardPeriodState<BigUint> {
+    /// Number of periods that ended, including `ended_period`.
+    pub num_periods: usize,
+
+    /// The period that ends now, not yet saved.
+    pub ended_period: Option<RewardPeriod<BigUint>>,
+
+    /// Block nonce when the current period started.
+    pub start_block: u64,
+
+    /// Sum of the delegation cap over each block of the current period so far.
+    pub cap_blocks: BigUint,
+
+    /// Sum of the total Active stake over each block of the current period so far.
+    pub active_blocks: BigUint,
+
+    /// Total cumulated rewards when the last period ended.
+    pub rewards_checkpoint: BigUint,
+
+    /// Rewards for the stake missing from the delegation cap, in total.
+    pub total_missing_stake_rewards: BigUint,
+}
+
+/// Stake of a user in the period in which they were last updated.
+pub struct UserPeriodData<BigUint> {
+    pub period: usize,
+
+    /// Block nonce when the user was last updated.
+    pub mark_block: u64,
+
+    /// Sum of the user Active stake over each block of the period, until `mark_block`.
+    pub stake_blocks: BigUint,
+}
+
+/// Alternative way of attributing rewards to the Active stake.
+///
+/// By default, all rewards received since a user's checkpoint are multiplied by the user's current Active stake,
+/// so the owner rewards must also be computed before the total Active stake changes.
+///
+/// With time-weighted rewards, the rewards are attributed to the stake in proportion to the number of blocks it was active.
+/// Rewards cannot be observed when they arrive, so the blocks are split into reward periods,
+/// each ending the first time new rewards are observed in the contract.
+/// The delegator rewards of a period are split between the stake that was active in it, by the number of blocks,
+/// out of the delegation cap over the same blocks. The part of the delegation cap with no Active stake earns for the owner.
+///
+/// Each user remembers the period in which they were last updated and their stake in it, block by block,
+/// so they only need to be updated right before their own Active stake changes,
+/// which happens while their funds get swapped.
+/// The owner rewards for the stake missing from the cap accumulate globally.
+///
+/// Can be switched on at any time. Each user keeps their rewards from before the switch,
+/// computed the default way when they are next updated.
+/// Can only be switched off before any stake is active.
+#[elrond_wasm_derive::module(TimeWeightedRewardsModuleImpl)]
+pub trait TimeWeightedRewardsModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(RewardsModuleImpl)]
+    fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(SettingsModuleImpl)]
+    fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(ResetCheckpointsModuleImpl)]
+    fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
+
+    #[module(FundViewModuleImpl)]
+    fn fund_view_module(&self) -> FundViewModuleImpl<T, BigInt, BigUint>;
+
+    #[view(isTimeWeightedRewards)]
+    #[storage_get("tw_rewards")]
+    fn is_time_weighted_rewards(&self) -> bool;
+
+    #[storage_set("tw_rewards")]
+    fn set_time_weighted_rewards(&self, time_weighted: bool);
+
+    #[endpoint(setTimeWeightedRewards)]
+    fn set_time_weighted_rewards_endpoint(&self, time_weighted: bool) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change the rewards mode");
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "global checkpoint is in progress"
+        );
+
+        if time_weighted == self.is_time_weighted_rewards() {
+            return Ok(());
+        }
+
+        if time_weighted {
+            self.switch_on_time_weighted_rewards();
+        } else {
+            let total_active = self
+                .fund_view_module()
+                .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active);
+            require!(
+                total_active == 0 && self.rewards().get_total_cumulated_rewards() == 0,
+                "time-weighted rewards can only be switched off before any stake is active"
+            );
+            self.set_time_weighted_rewards(false);
+        }
+
+        Ok(())
+    }
+
+    /// The owner rewards depend on the total Active stake, so they are computed the default way right away.
+    /// Other users keep the same Active stake until they are next updated, so their rewards can wait.
+    /// No period can end before the switch, periods only end if rewards arrive after it.
+    fn switch_on_time_weighted_rewards(&self) {
+        self.rewards().compute_one_user_reward(OWNER_USER_ID);
+
+        let block_nonce = self.get_block_nonce();
+        let total_cumulated_rewards = self.rewards().get_total_cumulated_rewards();
+        self.set_switch_rewards_checkpoint(&total_cumulated_rewards);
+        self.set_switch_block(block_nonce);
+        self.set_accumulator_checkpoint(&total_cumulated_rewards);
+        self.set_period_start_block(block_nonce);
+        self.set_last_update_block(block_nonce);
+        self.set_period_cap_blocks(&BigUint::zero());
+        self.set_period_active_blocks(&BigUint::zero());
+        self.set_owner_missing_stake_rewards_checkpoint(&self.get_total_missing_stake_rewards());
+
+        self.set_time_weighted_rewards(true);
+    }
+
+    /// Total cumulated rewards when time-weighted rewards were switched on.
+    /// Rewards received before are computed the default way.
+    #[storage_get("tw_since_checkp")]
+    fn get_switch_rewards_checkpoint(&self) -> BigUint;
+
+    #[storage_set("tw_since_checkp")]
+    fn set_switch_rewards_checkpoint(&self, cumulated_rewards: &BigUint);
+
+    /// Block nonce when time-weighted rewards were switched on.
+    #[storage_get("tw_since_block")]
+    fn get_switch_block(&self) -> u64;
+
+    #[storage_set("tw_since_block")]
+    fn set_switch_block(&self, block_nonce: u64);
+
+    // PERIODS
+
+    #[view(getNumRewardPeriods)]
+    #[storage_get("tw_n_periods")]
+    fn get_num_reward_periods(&self) -> usize;
+
+    #[storage_set("tw_n_periods")]
+    fn set_num_reward_periods(&self, num_periods: usize);
+
+    #[storage_get("tw_period_rate")]
+    fn get_period_reward_per_stake_block(&self, index: usize) -> BigUint;
+
+    #[storage_set("tw_period_rate")]
+    fn set_period_reward_per_stake_block(&self, index: usize, reward_per_stake_block: &BigUint);
+
+    #[storage_get("tw_period_end")]
+    fn get_period_end_block(&self, index: usize) -> u64;
+
+    #[storage_set("tw_period_end")]
+    fn set_period_end_block(&self, index: usize, end_block: u64);
+
+    #[storage_get("tw_period_rps")]
+    fn get_period_reward_per_stake(&self, index: usize) -> BigUint;
+
+    #[storage_set("tw_period_rps")]
+    fn set_period_reward_per_stake(&self, index: usize, reward_per_stake: &BigUint);
+
+    /// Yields the reward per stake and block, the end block nonce and the reward per stake of a period that ended.
+    #[view(getRewardPeriod)]
+    fn get_reward_period_endpoint(
+        &self,
+        index: usize,
+    ) -> SCResult<MultiResult3<BigUint, u64, BigUint>> {
+        require!(
+            index < self.get_num_reward_periods(),
+            "reward period not found"
+        );
+
+        let period = self.get_reward_period(index);
+        Ok((
+            period.reward_per_stake_block,
+            period.end_block,
+            period.reward_per_stake,
+        )
+            .into())
+    }
+
+    fn get_reward_period(&self, index: usize) -> RewardPeriod<BigUint> {
+        RewardPeriod {
+            reward_per_stake_block: self.get_period_reward_per_stake_block(index),
+            end_block: self.get_period_end_block(index),
+            reward_per_stake: self.get_period_reward_per_stake(index),
+        }
+    }
+
+    fn set_reward_period(&self, index: usize, period: &RewardPeriod<BigUint>) {
+        self.set_period_reward_per_stake_block(index, &period.reward_per_stake_block);
+        self.set_period_end_block(index, period.end_block);
+        self.set_period_reward_per_stake(index, &period.reward_per_stake);
+    }
+
+    /// Also yields the period that ends now, which is not saved yet.
+    fn load_reward_period(
+        &self,
+        state: &RewardPeriodState<BigUint>,
+        index: usize,
+    ) -> RewardPeriod<BigUint> {
+        if let Some(ended_period) = &state.ended_period {
+            if index + 1 == state.num_periods {
+                return ended_period.clone();
+            }
+        }
+        self.get_reward_period(index)
+    }
+
+    #[storage_get("tw_start")]
+    fn get_period_start_block(&self) -> u64;
+
+    #[storage_set("tw_start")]
+    fn set_period_start_block(&self, block_nonce: u64);
+
+    /// Block nonce when the block sums of the current period were last updated.
+    #[storage_get("tw_mark")]
+    fn get_last_update_block(&self) -> u64;
+
+    #[storage_set("tw_mark")]
+    fn set_last_update_block(&self, block_nonce: u64);
+
+    #[storage_get("tw_cap_blocks")]
+    fn get_period_cap_blocks(&self) -> BigUint;
+
+    #[storage_set("tw_cap_blocks")]
+    fn set_period_cap_blocks(&self, cap_blocks: &BigUint);
+
+    #[storage_get("tw_active_blocks")]
+    fn get_period_active_blocks(&self) -> BigUint;
+
+    #[storage_set("tw_active_blocks")]
+    fn set_period_active_blocks(&self, active_blocks: &BigUint);
+
+    /// Rewards received for the stake missing from the delegation cap, in total.
+    #[storage_get("tw_missing")]
+    fn get_total_missing_stake_rewards(&self) -> BigUint;
+
+    #[storage_set("tw_missing")]
+    fn set_total_missing_stake_rewards(&self, missing_stake_rewards: &BigUint);
+
+    /// Total cumulated rewards when the last period ended.
+    #[storage_get("tw_checkp")]
+    fn get_accumulator_checkpoint(&self) -> BigUint;
+
+    #[storage_set("tw_checkp")]
+    fn set_accumulator_checkpoint(&self, cumulated_rewards: &BigUint);
+
+    /// Brings the block sums up to the current block, with the delegation cap and total Active stake,
+    /// and ends the current period if new rewards arrived.
+    /// Only correct if neither changed since the last update.
+    /// Does not update storage.
+    fn load_updated_period_state(&self) -> RewardPeriodState<BigUint> {
+        let block_nonce = self.get_block_nonce();
+        let elapsed_blocks = BigUint::from(block_nonce - self.get_last_update_block());
+
+        let mut cap_blocks = self.settings().get_total_delegation_cap();
+        cap_blocks *= &elapsed_blocks;
+        cap_blocks += self.get_period_cap_blocks();
+
+        let mut active_blocks = self.rewards().get_total_active_stake();
+        active_blocks *= &elapsed_blocks;
+        active_blocks += self.get_period_active_blocks();
+
+        let mut state = RewardPeriodState {
+            num_periods: self.get_num_reward_periods(),
+            ended_period: None,
+            start_block: self.get_period_start_block(),
+            cap_blocks,
+            active_blocks,
+            rewards_checkpoint: self.get_accumulator_checkpoint(),
+            total_missing_stake_rewards: self.get_total_missing_stake_rewards(),
+        };
+
+        let total_cumulated_rewards = self.rewards().get_total_cumulated_rewards();
+        if total_cumulated_rewards == state.rewards_checkpoint || state.cap_blocks == 0 {
+            // no new rewards, or no stake could have earned them yet
+            return state;
+        }
+
+        let mut delegators_rewards = BigUint::zero();
+        for slice in self
+            .rewards()
+            .split_rewards_between(&state.rewards_checkpoint, &total_cumulated_rewards)
+            .iter()
+        {
+            delegators_rewards += &slice.total_delegators_rewards;
+        }
+
+        let mut reward_per_stake_block = delegators_rewards.clone();
+        reward_per_stake_block *= &BigUint::from(REWARD_PER_STAKE_PRECISION);
+        reward_per_stake_block /= &state.cap_blocks;
+
+        let mut reward_per_stake = reward_per_stake_block.clone();
+        reward_per_stake *= &BigUint::from(block_nonce - state.start_block);
+        if state.num_periods > 0 {
+            reward_per_stake += self.get_period_reward_per_stake(state.num_periods - 1);
+        }
+
+        let mut missing_stake_rewards = delegators_rewards;
+        missing_stake_rewards *= &(&state.cap_blocks - &state.active_blocks);
+        missing_stake_rewards /= &state.cap_blocks;
+        state.total_missing_stake_rewards += missing_stake_rewards;
+
+        state.ended_period = Some(RewardPeriod {
+            reward_per_stake_block,
+            end_block: block_nonce,
+            reward_per_stake,
+        });
+        state.num_periods += 1;
+        state.start_block = block_nonce;
+        state.cap_blocks = BigUint::zero();
+        state.active_blocks = BigUint::zero();
+        state.rewards_checkpoint = total_cumulated_rewards;
+        state
+    }
+
+    /// Must be called right before the delegation cap or the total Active stake changes.
+    /// Does nothing if rewards are not time-weighted.
+    fn update_accumulators(&self) {
+        if !self.is_time_weighted_rewards() {
+            return;
+        }
+
+        let state = self.load_updated_period_state();
+        if let Some(ended_period) = &state.ended_period {
+            self.set_reward_period(state.num_periods - 1, ended_period);
+        }
+        self.set_num_reward_periods(state.num_periods);
+        self.set_period_start_block(state.start_block);
+        self.set_last_update_block(self.get_block_nonce());
+        self.set_period_cap_blocks(&state.cap_blocks);
+        self.set_period_active_blocks(&state.active_blocks);
+        self.set_accumulator_checkpoint(&state.rewards_checkpoint);
+        self.set_total_missing_stake_rewards(&state.total_missing_stake_rewards);
+    }
+
+    // USERS
+
+    #[storage_get("u_tw_period")]
+    fn get_user_period(&self, user_id: NonZeroUsize) -> usize;
+
+    #[storage_set("u_tw_period")]
+    fn set_user_period(&self, user_id: NonZeroUsize, period: usize);
+
+    #[storage_get("u_tw_mark")]
+    fn get_user_mark_block(&self, user_id: NonZeroUsize) -> u64;
+
+    #[storage_set("u_tw_mark")]
+    fn set_user_mark_block(&self, user_id: NonZeroUsize, block_nonce: u64);
+
+    #[storage_get("u_tw_stake_blocks")]
+    fn get_user_stake_blocks(&self, user_id: NonZeroUsize) -> BigUint;
+
+    #[storage_set("u_tw_stake_blocks")]
+    fn set_user_stake_blocks(&self, user_id: NonZeroUsize, stake_blocks: &BigUint);
+
+    /// The missing stake rewards accumulator when the owner rewards were last computed.
+    #[storage_get("owner_tw_missing")]
+    fn get_owner_missing_stake_rewards_checkpoint(&self) -> BigUint;
+
+    #[storage_set("owner_tw_missing")]
+    fn set_owner_missing_stake_rewards_checkpoint(&self, missing_stake_rewards: &BigUint);
+
+    /// Users not updated since the switch start from it.
+    fn load_user_period_data(&self, user_id: NonZeroUsize) -> UserPeriodData<BigUint> {
+        let mark_block = self.get_user_mark_block(user_id);
+        let switch_block = self.get_switch_block();
+        if mark_block <= switch_block {
+            return UserPeriodData {
+                period: 0,
+                mark_block: switch_block,
+                stake_blocks: BigUint::zero(),
+            };
+        }
+
+        UserPeriodData {
+            period: self.get_user_period(user_id),
+            mark_block,
+            stake_blocks: self.get_user_stake_blocks(user_id),
+        }
+    }
+
+    /// Yields the rewards of the user Active stake in the periods that ended since the user was last updated,
+    /// and the user data brought up to the current block.
+    /// The user Active stake must not have changed since the last update.
+    fn load_updated_user_period_data(
+        &self,
+        user_id: NonZeroUsize,
+        state: &RewardPeriodState<BigUint>,
+    ) -> (BigUint, UserPeriodData<BigUint>) {
+        let mut data = self.load_user_period_data(user_id);
+        let stake = self
+            .fund_view_module()
+            .get_user_stake_of_type(user_id.get(), FundType::Active);
+
+        let mut stake_rewards = BigUint::zero();
+        if data.period < state.num_periods {
+            // the rest of the period in which the user was last updated
+            let user_period = self.load_reward_period(state, data.period);
+            let mut stake_blocks = stake.clone();
+            stake_blocks *= &BigUint::from(user_period.end_block - data.mark_block);
+            stake_blocks += &data.stake_blocks;
+            stake_rewards += &user_period.reward_per_stake_block * &stake_blocks;
+
+            // whole periods since
+            let last_period = self.load_reward_period(state, state.num_periods - 1);
+            let mut later_rewards = &last_period.reward_per_stake - &user_period.reward_per_stake;
+            later_rewards *= &stake;
+            stake_rewards += later_rewards;
+            stake_rewards /= &BigUint::from(REWARD_PER_STAKE_PRECISION);
+
+            data.period = state.num_periods;
+            data.mark_block = last_period.end_block;
+            data.stake_blocks = BigUint::zero();
+        }
+
+        // the current period, until now
+        let block_nonce = self.get_block_nonce();
+        let mut stake_blocks = stake;
+        stake_blocks *= &BigUint::from(block_nonce - data.mark_block);
+        data.stake_blocks += stake_blocks;
+        data.mark_block = block_nonce;
+
+        (stake_rewards, data)
+    }
+
+    /// Yields the rewards for the user Active stake and, for the owner, the missing stake rewards,
+    /// since the user rewards were last computed. The service fee is not included.
+    /// Does not update storage.
+    fn load_accumulated_user_rewards(&self, user_id: NonZeroUsize) -> (BigUint, BigUint) {
+        let state = self.load_updated_period_state();
+        let (stake_rewards, _) = self.load_updated_user_period_data(user_id, &state);
+
+        let user_missing_stake_rewards = if user_id == OWNER_USER_ID {
+            state.total_missing_stake_rewards - self.get_owner_missing_stake_rewards_checkpoint()
+        } else {
+            BigUint::zero()
+        };
+
+        (stake_rewards, user_missing_stake_rewards)
+    }
+
+    /// Saves the user data brought up to the current block.
+    /// Called when the user rewards are stored, after adding the accumulated rewards to them.
+    fn store_user_accumulators(&self, user_id: NonZeroUsize) {
+        self.update_accumulators();
+        let state = self.load_updated_period_state();
+        let (_, data) = self.load_updated_user_period_data(user_id, &state);
+        self.set_user_period(user_id, data.period);
+        self.set_user_mark_block(user_id, data.mark_block);
+        self.set_user_stake_blocks(user_id, &data.stake_blocks);
+        if user_id == OWNER_USER_ID {
+            self.set_owner_missing_stake_rewards_checkpoint(&state.total_missing_stake_rewards);
+        }
+    }
+
+    /// Brings a user up to date right before their Active stake changes.
+    /// Does nothing if rewards are not time-weighted.
+    fn before_user_stake_change(&self, user_id: usize) {
+        if !self.is_time_weighted_rewards() {
+            return;
+        }
+
+        if let Some(user_id_nz) = NonZeroUsize::new(user_id) {
+            self.rewards().compute_one_user_reward(user_id_nz);
+        }
+    }
+}
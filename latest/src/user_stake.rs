@@ -7,6 +7,7 @@ use crate::events::*;
 use crate::reset_checkpoints::*;
 use crate::rewards::*;
 use crate::settings::*;
+use crate::time_weighted_rewards::*;
 
 use core::num::NonZeroUsize;
 
@@ -36,6 +37,9 @@ pub trait UserStakeModule {
     #[module(SettingsModuleImpl)]
     fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
 
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     #[module(EventsModuleImpl)]
     fn events(&self) -> EventsModuleImpl<T, BigInt, BigUint>;
 
@@ -93,9 +97,16 @@ pub trait UserStakeModule {
 
             // swap waiting -> active, but no more than fillable
             // no need to worry about rewards here, because there aren't any
-            let _ = self
-                .fund_transf_module()
-                .swap_waiting_to_active(&mut fillable_active_stake, || false);
+            // time-weighted rewards still count the blocks each user stake was active
+            self.time_weighted_rewards().update_accumulators();
+            let _ = self.fund_transf_module().swap_waiting_to_active(
+                &mut fillable_active_stake,
+                || false,
+                |user_id| {
+                    self.time_weighted_rewards()
+                        .before_user_stake_change(user_id)
+                },
+            );
             if fillable_active_stake == 0 {
                 // this happens only when waiting was enough to fill the delegation cap
                 self.settings().set_bootstrap_mode(false);
@@ -125,25 +136,36 @@ pub trait UserStakeModule {
 
     /// Swaps waiting stake to active within given limits,
     /// and also computes rewards for all affected users before performing the swap itself.
+    /// With time-weighted rewards, the owner rewards do not depend on it,
+    /// and each affected user is brought up to date while their funds get swapped.
     fn swap_waiting_to_active_compute_rewards(&self, swappable: &BigUint) -> SCResult<()> {
-        // dry run of swap, to get the affected users
-        let (affected_users, remaining) = self
-            .fund_transf_module()
-            .get_affected_users_of_swap_waiting_to_active(swappable, || false);
-        require!(remaining == 0, "error swapping waiting to active");
-
-        // compute rewards for all affected users
-        self.rewards().compute_one_user_reward(OWNER_USER_ID);
-        for user_id in affected_users.iter() {
-            let user_id_nz = non_zero_usize!(*user_id, "bad user_id");
-            self.rewards().compute_one_user_reward(user_id_nz);
+        if self.time_weighted_rewards().is_time_weighted_rewards() {
+            self.time_weighted_rewards().update_accumulators();
+        } else {
+            // dry run of swap, to get the affected users
+            let (affected_users, remaining) = self
+                .fund_transf_module()
+                .get_affected_users_of_swap_waiting_to_active(swappable, || false);
+            require!(remaining == 0, "error swapping waiting to active");
+
+            // compute rewards for all affected users
+            self.rewards().compute_one_user_reward(OWNER_USER_ID);
+            for user_id in affected_users.iter() {
+                let user_id_nz = non_zero_usize!(*user_id, "bad user_id");
+                self.rewards().compute_one_user_reward(user_id_nz);
+            }
         }
 
         // actual swap of waiting to active
         let mut remaining = swappable.clone();
-        let _ = self
-            .fund_transf_module()
-            .swap_waiting_to_active(&mut remaining, || false);
+        let _ = self.fund_transf_module().swap_waiting_to_active(
+            &mut remaining,
+            || false,
+            |user_id| {
+                self.time_weighted_rewards()
+                    .before_user_stake_change(user_id)
+            },
+        );
         require!(remaining == 0, "error swapping waiting to active");
 
         Ok(())
@@ -169,6 +191,83 @@ pub trait UserStakeModule {
         self.process_stake(payment)
     }
 
+    /// Turns all the unclaimed rewards of a user into Waiting stake,
+    /// same as claiming them and staking them again, but without the funds leaving the contract.
+    /// The minimum stake does not apply to the re-delegated amount.
+    /// Yields the re-delegated amount.
+    fn re_delegate_user_rewards(
+        &self,
+        user_id: NonZeroUsize,
+        user_address: &Address,
+    ) -> SCResult<BigUint> {
+        // checked before anything changes, Waiting stake does not count
+        sc_try!(self.validate_delegation_cap_invariant());
+
+        let mut user_data = self.rewards().load_updated_user_rewards(user_id);
+        let amount = core::mem::replace(&mut user_data.unclaimed_rewards, BigUint::zero());
+        self.rewards().store_user_reward_data(user_id, &user_data);
+        if amount == 0 {
+            return Ok(amount);
+        }
+
+        // the rewards leave the reward pool, but stay in the contract as stake
+        self.rewards().add_sent_rewards(&amount);
+
+        self.events()
+            .re_delegate_rewards_event(user_address, &amount);
+
+        self.fund_transf_module()
+            .create_waiting(user_id.get(), amount.clone());
+
+        sc_try!(self.use_waiting_to_replace_unstaked());
+
+        Ok(amount)
+    }
+
+    /// Stakes the caller's rewards instead of claiming them.
+    #[endpoint(reDelegateRewards)]
+    fn re_delegate_rewards(&self) -> SCResult<()> {
+        require!(self.pause().not_paused(), "contract paused");
+
+        let caller = self.get_caller();
+        let user_id = non_zero_usize!(self.user_data().get_user_id(&caller), "unknown caller");
+
+        require!(
+            !self.reset_checkpoints().is_global_op_in_progress(),
+            "re-delegating rewards is temporarily paused as checkpoint is reset"
+        );
+
+        let amount = sc_try!(self.re_delegate_user_rewards(user_id, &caller));
+        require!(amount > 0, "no rewards to re-delegate");
+
+        Ok(())
+    }
+
+    /// If set, rewards pushed by `distributeRewards` or claimed on behalf of the user are re-delegated instead of sent.
+    /// The user can still claim them to their own wallet with `claimRewards`.
+    #[storage_get("auto_compound")]
+    fn is_auto_compound(&self, user_id: NonZeroUsize) -> bool;
+
+    #[storage_set("auto_compound")]
+    fn set_auto_compound(&self, user_id: NonZeroUsize, auto_compound: bool);
+
+    #[view(isAutoCompound)]
+    fn is_auto_compound_endpoint(&self, user_address: Address) -> bool {
+        if let Some(user_id) = NonZeroUsize::new(self.user_data().get_user_id(&user_address)) {
+            self.is_auto_compound(user_id)
+        } else {
+            false
+        }
+    }
+
+    #[endpoint(setAutoCompound)]
+    fn set_auto_compound_endpoint(&self, auto_compound: bool) -> SCResult<()> {
+        let caller = self.get_caller();
+        let user_id = non_zero_usize!(self.user_data().get_user_id(&caller), "unknown caller");
+        self.set_auto_compound(user_id, auto_compound);
+        Ok(())
+    }
+
     /// Mostly invariant: modifyTotalDelegationCap can violate this rule.
     fn validate_user_minimum_stake(&self, user_id: usize) -> SCResult<()> {
         let waiting = self
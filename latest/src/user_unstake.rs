@@ -7,6 +7,7 @@ use crate::events::*;
 use crate::reset_checkpoints::*;
 use crate::rewards::*;
 use crate::settings::*;
+use crate::time_weighted_rewards::*;
 use crate::user_stake::*;
 
 use core::cmp::Ordering;
@@ -43,6 +44,9 @@ pub trait UserUnStakeModule {
     #[module(SettingsModuleImpl)]
     fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
 
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     fn validate_unstake_amount(&self, user_id: usize, amount: &BigUint) -> SCResult<()> {
         let max_unstake = self
             .fund_view_module()
@@ -95,7 +99,10 @@ pub trait UserUnStakeModule {
         }
 
         // compute rewards before converting Active -> UnStaked
-        self.rewards().compute_one_user_reward(OWNER_USER_ID);
+        // time-weighted rewards of the owner do not depend on it
+        if !self.time_weighted_rewards().is_time_weighted_rewards() {
+            self.rewards().compute_one_user_reward(OWNER_USER_ID);
+        }
         self.rewards().compute_one_user_reward(unstake_user_id);
 
         // convert Active -> UnStaked
@@ -28,12 +28,21 @@ pub trait Delegation {
     #[module(NodeConfigModuleImpl)]
     fn node_config(&self) -> NodeConfigModuleImpl<T, BigInt, BigUint>;
 
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     #[module(RewardsModuleImpl)]
     fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
 
     #[module(ResetCheckpointsModuleImpl)]
     fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
 
+    #[module(RewardHistoryModuleImpl)]
+    fn reward_history(&self) -> RewardHistoryModuleImpl<T, BigInt, BigUint>;
+
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     #[module(SettingsModuleImpl)]
     fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
 
@@ -46,6 +55,9 @@ pub trait Delegation {
     #[module(NodeActivationModuleImpl)]
     fn node_activation(&self) -> NodeActivationModuleImpl<T, BigInt, BigUint>;
 
+    #[module(OwnershipModuleImpl)]
+    fn ownership(&self) -> OwnershipModuleImpl<T, BigInt, BigUint>;
+
     #[module(UserUnStakeModuleImpl)]
     fn user_unstake(&self) -> UserUnStakeModuleImpl<T, BigInt, BigUint>;
 
@@ -110,10 +122,22 @@ pub trait Delegation {
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) {
+        let result = self
+            .node_activation()
+            .auction_stake_callback(node_ids.clone(), call_result);
         self.node_activation()
-            .auction_stake_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .save_callback_result(node_ids.as_slice(), result);
+    }
+
+    #[callback]
+    fn auction_top_up_callback(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] amount_per_node: BigUint,
+        call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
+    ) {
+        self.node_activation()
+            .auction_top_up_callback(node_bls_keys, amount_per_node, call_result);
     }
 
     #[callback]
@@ -122,10 +146,11 @@ pub trait Delegation {
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) {
+        let result = self
+            .node_activation()
+            .auction_unstake_callback(node_ids.clone(), call_result);
         self.node_activation()
-            .auction_unstake_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .save_callback_result(node_ids.as_slice(), result);
     }
 
     #[callback]
@@ -134,9 +159,47 @@ pub trait Delegation {
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) {
+        let result = self
+            .node_activation()
+            .auction_unbond_callback(node_ids.clone(), call_result);
+        self.node_activation()
+            .save_callback_result(node_ids.as_slice(), result);
+    }
+
+    #[callback]
+    fn auction_bls_keys_status_callback(
+        &self,
+        #[callback_arg] fix_mismatches: bool,
+        call_result: AsyncCallResult<VarArgs<BLSAuctionStatusMultiArg>>,
+    ) {
+        self.node_activation()
+            .auction_bls_keys_status_callback(fix_mismatches, call_result);
+    }
+
+    #[callback]
+    fn auction_total_staked_callback(&self, call_result: AsyncCallResult<BigUint>) {
         self.node_activation()
-            .auction_unbond_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .auction_total_staked_callback(call_result);
+    }
+
+    #[callback]
+    fn auction_claim_callback(&self, call_result: AsyncCallResult<()>) {
+        self.node_activation().auction_claim_callback(call_result);
+    }
+
+    #[callback]
+    fn auction_unjail_callback(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] fine_payment: BigUint,
+        #[callback_arg] fine_payer: Address,
+        call_result: AsyncCallResult<()>,
+    ) {
+        self.node_activation().auction_unjail_callback(
+            node_bls_keys,
+            fine_payment,
+            fine_payer,
+            call_result,
+        );
     }
 }
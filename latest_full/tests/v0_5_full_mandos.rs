@@ -35,6 +35,11 @@ fn activate_nodes() {
     parse_execute_mandos("mandos/activate_nodes.scen.json", &contract_map());
 }
 
+#[test]
+fn callback_errors() {
+    parse_execute_mandos("mandos/callback_errors.scen.json", &contract_map());
+}
+
 #[test]
 fn change_service_fee() {
     parse_execute_mandos("mandos/change_service_fee.scen.json", &contract_map());
@@ -45,6 +50,11 @@ fn claim_rewards_1() {
     parse_execute_mandos("mandos/claim_rewards_1.scen.json", &contract_map());
 }
 
+#[test]
+fn claim_rewards_for() {
+    parse_execute_mandos("mandos/claim_rewards_for.scen.json", &contract_map());
+}
+
 #[test]
 fn claim_rewards_owner_with_stake() {
     parse_execute_mandos(
@@ -93,11 +103,76 @@ fn decrease_cap_in_bootstrap_mode() {
     );
 }
 
+#[test]
+fn distribute_rewards() {
+    parse_execute_mandos("mandos/distribute_rewards.scen.json", &contract_map());
+}
+
 #[test]
 fn increase_delegation_cap() {
     parse_execute_mandos("mandos/increase_delegation_cap.scen.json", &contract_map());
 }
 
+#[test]
+fn jail() {
+    parse_execute_mandos("mandos/jail.scen.json", &contract_map());
+}
+
+#[test]
+fn node_info() {
+    parse_execute_mandos("mandos/node_info.scen.json", &contract_map());
+}
+
+#[test]
+fn node_states_page() {
+    parse_execute_mandos("mandos/node_states_page.scen.json", &contract_map());
+}
+
+#[test]
+fn operators() {
+    parse_execute_mandos("mandos/operators.scen.json", &contract_map());
+}
+
+#[test]
+fn ownership_transfer() {
+    parse_execute_mandos("mandos/ownership_transfer.scen.json", &contract_map());
+}
+
+#[test]
+fn purge_removed_nodes() {
+    parse_execute_mandos("mandos/purge_removed_nodes.scen.json", &contract_map());
+}
+
+#[test]
+fn reconcile_node_states() {
+    parse_execute_mandos("mandos/reconcile_node_states.scen.json", &contract_map());
+}
+
+#[test]
+fn re_delegate_rewards() {
+    parse_execute_mandos("mandos/re_delegate_rewards.scen.json", &contract_map());
+}
+
+#[test]
+fn recover_pending_nodes() {
+    parse_execute_mandos("mandos/recover_pending_nodes.scen.json", &contract_map());
+}
+
+#[test]
+fn returned_from_auction() {
+    parse_execute_mandos("mandos/returned_from_auction.scen.json", &contract_map());
+}
+
+#[test]
+fn reward_history() {
+    parse_execute_mandos("mandos/reward_history.scen.json", &contract_map());
+}
+
+#[test]
+fn reward_regimes() {
+    parse_execute_mandos("mandos/reward_regimes.scen.json", &contract_map());
+}
+
 #[test]
 fn rewards_for_unstaked_go_to_the_owner() {
     parse_execute_mandos(
@@ -106,6 +181,29 @@ fn rewards_for_unstaked_go_to_the_owner() {
     );
 }
 
+#[test]
+fn rotate_node_key() {
+    parse_execute_mandos("mandos/rotate_node_key.scen.json", &contract_map());
+}
+
+#[test]
+fn schedule_service_fee_change() {
+    parse_execute_mandos(
+        "mandos/schedule_service_fee_change.scen.json",
+        &contract_map(),
+    );
+}
+
+#[test]
+fn service_fee_destination() {
+    parse_execute_mandos("mandos/service_fee_destination.scen.json", &contract_map());
+}
+
+#[test]
+fn service_fee_tiers() {
+    parse_execute_mandos("mandos/service_fee_tiers.scen.json", &contract_map());
+}
+
 #[test]
 fn staking_1() {
     parse_execute_mandos("mandos/staking_1.scen.json", &contract_map());
@@ -116,6 +214,29 @@ fn staking_2() {
     parse_execute_mandos("mandos/staking_2.scen.json", &contract_map());
 }
 
+#[test]
+fn sweep_dust() {
+    parse_execute_mandos("mandos/sweep_dust.scen.json", &contract_map());
+}
+
+#[test]
+fn time_weighted_rewards() {
+    parse_execute_mandos("mandos/time_weighted_rewards.scen.json", &contract_map());
+}
+
+#[test]
+fn time_weighted_rewards_switch() {
+    parse_execute_mandos(
+        "mandos/time_weighted_rewards_switch.scen.json",
+        &contract_map(),
+    );
+}
+
+#[test]
+fn top_up_nodes() {
+    parse_execute_mandos("mandos/top_up_nodes.scen.json", &contract_map());
+}
+
 #[test]
 fn total_funds_getters() {
     parse_execute_mandos("mandos/total_funds_getters.scen.json", &contract_map());
@@ -161,7 +282,17 @@ fn unstake_5_backwards() {
     parse_execute_mandos("mandos/unstake_5_backwards.scen.json", &contract_map());
 }
 
+#[test]
+fn user_claim_history() {
+    parse_execute_mandos("mandos/user_claim_history.scen.json", &contract_map());
+}
+
 #[test]
 fn user_fund_getters() {
     parse_execute_mandos("mandos/user_fund_getters.scen.json", &contract_map());
 }
+
+#[test]
+fn verify_node_signature() {
+    parse_execute_mandos("mandos/verify_node_signature.scen.json", &contract_map());
+}
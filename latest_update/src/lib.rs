@@ -28,12 +28,21 @@ pub trait Delegation {
     #[module(NodeConfigModuleImpl)]
     fn node_config(&self) -> NodeConfigModuleImpl<T, BigInt, BigUint>;
 
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     #[module(RewardsModuleImpl)]
     fn rewards(&self) -> RewardsModuleImpl<T, BigInt, BigUint>;
 
     #[module(ResetCheckpointsModuleImpl)]
     fn reset_checkpoints(&self) -> ResetCheckpointsModuleImpl<T, BigInt, BigUint>;
 
+    #[module(RewardHistoryModuleImpl)]
+    fn reward_history(&self) -> RewardHistoryModuleImpl<T, BigInt, BigUint>;
+
+    #[module(TimeWeightedRewardsModuleImpl)]
+    fn time_weighted_rewards(&self) -> TimeWeightedRewardsModuleImpl<T, BigInt, BigUint>;
+
     #[module(SettingsModuleImpl)]
     fn settings(&self) -> SettingsModuleImpl<T, BigInt, BigUint>;
 
@@ -46,6 +55,9 @@ pub trait Delegation {
     #[module(NodeActivationModuleImpl)]
     fn node_activation(&self) -> NodeActivationModuleImpl<T, BigInt, BigUint>;
 
+    #[module(OwnershipModuleImpl)]
+    fn ownership(&self) -> OwnershipModuleImpl<T, BigInt, BigUint>;
+
     #[module(UserUnStakeModuleImpl)]
     fn user_unstake(&self) -> UserUnStakeModuleImpl<T, BigInt, BigUint>;
 
@@ -78,9 +90,32 @@ pub trait Delegation {
         }
     }
 
+    /// previous versions did not keep track of the stake sent to and returned from the auction SC,
+    /// the unprotected funds were inferred from the contract balance instead
+    /// the stake currently in the auction SC is inferred here the same way, to initialize the counters
+    fn update_stake_in_auction_if_necessary(&self) {
+        if self.rewards().get_total_sent_to_auction() == 0
+            && self.rewards().get_total_returned_from_auction() == 0
+        {
+            let total_active = self
+                .fund_view_module()
+                .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::Active);
+            let total_unstaked = self
+                .fund_view_module()
+                .get_user_stake_of_type(USER_STAKE_TOTALS_ID, FundType::UnStaked);
+            let total_stake = total_active + total_unstaked;
+            let total_unprotected = self.rewards().total_unprotected_from_balance();
+            if total_stake > total_unprotected {
+                self.rewards()
+                    .set_total_sent_to_auction(&(total_stake - total_unprotected));
+            }
+        }
+    }
+
     #[init]
     fn init(&self) -> SCResult<()> {
         self.update_total_delegation_cap_if_necessary();
+        self.update_stake_in_auction_if_necessary();
         Ok(())
     }
 
@@ -91,11 +126,23 @@ pub trait Delegation {
         &self,
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
+    ) {
+        let result = self
+            .node_activation()
+            .auction_stake_callback(node_ids.clone(), call_result);
+        self.node_activation()
+            .save_callback_result(node_ids.as_slice(), result);
+    }
+
+    #[callback]
+    fn auction_top_up_callback(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] amount_per_node: BigUint,
+        call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) {
         self.node_activation()
-            .auction_stake_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .auction_top_up_callback(node_bls_keys, amount_per_node, call_result);
     }
 
     #[callback]
@@ -104,10 +151,11 @@ pub trait Delegation {
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
     ) {
+        let result = self
+            .node_activation()
+            .auction_unstake_callback(node_ids.clone(), call_result);
         self.node_activation()
-            .auction_unstake_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .save_callback_result(node_ids.as_slice(), result);
     }
 
     #[callback]
@@ -115,10 +163,48 @@ pub trait Delegation {
         &self,
         #[callback_arg] node_ids: Vec<usize>,
         call_result: AsyncCallResult<VarArgs<BLSStatusMultiArg>>,
+    ) {
+        let result = self
+            .node_activation()
+            .auction_unbond_callback(node_ids.clone(), call_result);
+        self.node_activation()
+            .save_callback_result(node_ids.as_slice(), result);
+    }
+
+    #[callback]
+    fn auction_bls_keys_status_callback(
+        &self,
+        #[callback_arg] fix_mismatches: bool,
+        call_result: AsyncCallResult<VarArgs<BLSAuctionStatusMultiArg>>,
     ) {
         self.node_activation()
-            .auction_unbond_callback(node_ids, call_result)
-            .unwrap();
-        // TODO: replace unwrap with typical Result handling
+            .auction_bls_keys_status_callback(fix_mismatches, call_result);
+    }
+
+    #[callback]
+    fn auction_total_staked_callback(&self, call_result: AsyncCallResult<BigUint>) {
+        self.node_activation()
+            .auction_total_staked_callback(call_result);
+    }
+
+    #[callback]
+    fn auction_claim_callback(&self, call_result: AsyncCallResult<()>) {
+        self.node_activation().auction_claim_callback(call_result);
+    }
+
+    #[callback]
+    fn auction_unjail_callback(
+        &self,
+        #[callback_arg] node_bls_keys: Vec<BLSKey>,
+        #[callback_arg] fine_payment: BigUint,
+        #[callback_arg] fine_payer: Address,
+        call_result: AsyncCallResult<()>,
+    ) {
+        self.node_activation().auction_unjail_callback(
+            node_bls_keys,
+            fine_payment,
+            fine_payer,
+            call_result,
+        );
     }
 }
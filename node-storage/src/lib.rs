@@ -7,8 +7,23 @@ pub mod types;
 
 // modules
 pub mod node_config;
+pub mod operators;
 
 #[macro_use]
 extern crate elrond_wasm;
 
+/// Same as `only_owner!`, but checks the delegation owner, which follows ownership transfers.
+/// The module needs to reference the operators module.
+#[macro_export]
+macro_rules! only_delegation_owner {
+    ($trait_self: expr, $error_msg:expr) => {
+        if !$trait_self
+            .operators()
+            .is_delegation_owner(&$trait_self.get_caller())
+        {
+            return sc_error!($error_msg);
+        }
+    };
+}
+
 imports!();
@@ -1,7 +1,18 @@
+use crate::only_delegation_owner;
+use crate::operators::*;
 use crate::types::*;
 
 imports!();
 
+/// Maximum length of the node label set by the owner, in bytes.
+pub const MAX_NODE_LABEL_LENGTH: usize = 32;
+
+/// Maximum length of the node hosting region set by the owner, in bytes.
+pub const MAX_NODE_REGION_LENGTH: usize = 32;
+
+/// Maximum length of the arbitrary node metadata set by the owner, in bytes.
+pub const MAX_NODE_METADATA_LENGTH: usize = 256;
+
 /// Indicates how we express the percentage of rewards that go to the node.
 /// Since we cannot have floating point numbers, we use fixed point with this denominator.
 /// Percents + 2 decimals -> 10000.
@@ -14,6 +25,9 @@ imports!();
 ///
 #[elrond_wasm_derive::module(NodeConfigModuleImpl)]
 pub trait NodeModule {
+    #[module(OperatorsModuleImpl)]
+    fn operators(&self) -> OperatorsModuleImpl<T, BigInt, BigUint>;
+
     /// The number of nodes that will run with the contract stake, as configured by the owner.
     #[view(getNumNodes)]
     #[storage_get("num_nodes")]
@@ -33,18 +47,62 @@ pub trait NodeModule {
     #[storage_set("node_bls_to_id")]
     fn set_node_bls_to_id(&self, bls_key: &BLSKey, node_id: usize);
 
+    #[storage_clear("node_bls_to_id")]
+    fn clear_node_bls_to_id(&self, bls_key: &BLSKey);
+
     #[storage_get("node_id_to_bls")]
     fn get_node_id_to_bls(&self, node_id: usize) -> BLSKey;
 
     #[storage_set("node_id_to_bls")]
     fn set_node_id_to_bls(&self, node_id: usize, bls_key: &BLSKey);
 
+    #[storage_clear("node_id_to_bls")]
+    fn clear_node_id_to_bls(&self, node_id: usize);
+
+    /// Ids of the given nodes, skipping the ones that are not registered.
+    fn get_registered_node_ids(&self, bls_keys: &[BLSKey]) -> Vec<usize> {
+        bls_keys
+            .iter()
+            .map(|bls_key| self.get_node_id(bls_key))
+            .filter(|&node_id| node_id != 0)
+            .collect()
+    }
+
     #[storage_get("node_signature")]
     fn get_node_signature(&self, node_id: usize) -> BLSSignature;
 
     #[storage_set("node_signature")]
     fn set_node_signature(&self, node_id: usize, node_signature: BLSSignature);
 
+    #[storage_clear("node_signature")]
+    fn clear_node_signature(&self, node_id: usize);
+
+    /// Verification hook for the proof of possession of a BLS key:
+    /// the signature should be of the contract address, with the BLS key.
+    ///
+    /// STUB: the VM does not expose BLS signature verification to contracts yet,
+    /// so for now this only rejects keys and signatures that cannot be valid, i.e. all zero bytes.
+    /// It does NOT verify the signature, which is only really checked by the auction SC, when the node is staked.
+    /// Only the body needs to change once the VM provides the BLS verify API.
+    fn verify_bls_signature(&self, bls_key: &BLSKey, bls_sig: &BLSSignature) -> bool {
+        bls_key.0.iter().any(|&b| b != 0) && bls_sig.0.iter().any(|&b| b != 0)
+    }
+
+    /// Checks the stored signature of a node with `verify_bls_signature`, same as when it was added,
+    /// so until the VM provides BLS verification it only checks that the signature is not all zero bytes.
+    /// Yields nothing if the node is not registered.
+    #[view(verifyNodeSignature)]
+    fn verify_node_signature(&self, bls_key: BLSKey) -> OptionalResult<bool> {
+        let node_id = self.get_node_id(&bls_key);
+        if node_id == 0 {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(
+                self.verify_bls_signature(&bls_key, &self.get_node_signature(node_id)),
+            )
+        }
+    }
+
     #[view(getNodeSignature)]
     fn get_node_signature_endpoint(&self, bls_key: BLSKey) -> OptionalResult<BLSSignature> {
         let node_id = self.get_node_id(&bls_key);
@@ -62,6 +120,9 @@ pub trait NodeModule {
     #[storage_set("node_state")]
     fn set_node_state(&self, node_id: usize, node_state: NodeState);
 
+    #[storage_clear("node_state")]
+    fn clear_node_state(&self, node_id: usize);
+
     #[view(getNodeState)]
     fn get_node_state_endpoint(&self, bls_key: BLSKey) -> NodeState {
         let node_id = self.get_node_id(&bls_key);
@@ -72,6 +133,46 @@ pub trait NodeModule {
         }
     }
 
+    /// Error message of the last auction SC callback that could not be processed for this node.
+    /// Empty if there is no unresolved callback error.
+    #[storage_get("node_cb_err")]
+    fn get_node_callback_error(&self, node_id: usize) -> BoxedBytes;
+
+    #[storage_set("node_cb_err")]
+    fn set_node_callback_error(&self, node_id: usize, err_msg: &[u8]);
+
+    #[storage_is_empty("node_cb_err")]
+    fn is_empty_node_callback_error(&self, node_id: usize) -> bool;
+
+    #[storage_clear("node_cb_err")]
+    fn clear_node_callback_error(&self, node_id: usize);
+
+    #[view(getNodeCallbackError)]
+    fn get_node_callback_error_endpoint(&self, bls_key: BLSKey) -> OptionalResult<BoxedBytes> {
+        let node_id = self.get_node_id(&bls_key);
+        if node_id == 0 || self.is_empty_node_callback_error(node_id) {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(self.get_node_callback_error(node_id))
+        }
+    }
+
+    /// Lists all nodes with unresolved callback errors, together with the error messages.
+    #[view(getNodesWithCallbackErrors)]
+    fn get_nodes_with_callback_errors(&self) -> MultiResultVec<MultiResult2<BLSKey, BoxedBytes>> {
+        let num_nodes = self.get_num_nodes();
+        let mut result = Vec::new();
+        for i in 1..num_nodes + 1 {
+            if !self.is_empty_node_callback_error(i) {
+                result.push(MultiResult2::from((
+                    self.get_node_id_to_bls(i),
+                    self.get_node_callback_error(i),
+                )));
+            }
+        }
+        result.into()
+    }
+
     #[view(getAllNodeStates)]
     fn get_all_node_states(&self) -> MultiResultVec<MultiResult2<BLSKey, u8>> {
         let num_nodes = self.get_num_nodes();
@@ -85,6 +186,139 @@ pub trait NodeModule {
         result.into()
     }
 
+    /// Same as `getAllNodeStates`, but only for at most `count` nodes, starting from node id `start_id`.
+    /// Node ids start from 1. The page is empty once `start_id` is past `getNumNodes`.
+    #[view(getNodeStatesPage)]
+    fn get_node_states_page(
+        &self,
+        start_id: usize,
+        count: usize,
+    ) -> MultiResultVec<MultiResult2<BLSKey, u8>> {
+        let mut result = Vec::new();
+        for i in self.node_id_page(start_id, count) {
+            result.push(MultiResult2::from((
+                self.get_node_id_to_bls(i),
+                self.get_node_state(i).discriminant(),
+            )));
+        }
+        result.into()
+    }
+
+    /// Looks at at most `count` nodes, starting from node id `start_id`,
+    /// and yields the BLS keys of the ones in the given state.
+    /// The state is given as discriminant, same as in `getAllNodeStates`.
+    /// The next page starts from `start_id + count`, even if fewer keys were returned.
+    #[view(getNodesInStatePage)]
+    fn get_nodes_in_state_page(
+        &self,
+        node_state_discriminant: u8,
+        start_id: usize,
+        count: usize,
+    ) -> MultiResultVec<BLSKey> {
+        let mut result = Vec::new();
+        for i in self.node_id_page(start_id, count) {
+            if self.get_node_state(i).discriminant() == node_state_discriminant {
+                result.push(self.get_node_id_to_bls(i));
+            }
+        }
+        result.into()
+    }
+
+    /// Ids of at most `count` nodes, starting from `start_id`.
+    fn node_id_page(&self, start_id: usize, count: usize) -> core::ops::Range<usize> {
+        let start_id = core::cmp::max(start_id, 1);
+        let end_id = core::cmp::min(start_id.saturating_add(count), self.get_num_nodes() + 1);
+        start_id..end_id
+    }
+
+    /// Stake sent to the auction SC for this node, including top-ups.
+    /// Zero if the node is not staked.
+    #[storage_get("node_stake")]
+    fn get_node_stake(&self, node_id: usize) -> BigUint;
+
+    #[storage_set("node_stake")]
+    fn set_node_stake(&self, node_id: usize, node_stake: &BigUint);
+
+    #[storage_clear("node_stake")]
+    fn clear_node_stake(&self, node_id: usize);
+
+    #[view(getNodeStake)]
+    fn get_node_stake_endpoint(&self, bls_key: BLSKey) -> OptionalResult<BigUint> {
+        let node_id = self.get_node_id(&bls_key);
+        if node_id == 0 {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(self.get_node_stake(node_id))
+        }
+    }
+
+    /// Lists all nodes that have stake in the auction SC, together with their stake.
+    #[view(getAllNodeStakes)]
+    fn get_all_node_stakes(&self) -> MultiResultVec<MultiResult2<BLSKey, BigUint>> {
+        let num_nodes = self.get_num_nodes();
+        let mut result = Vec::new();
+        for i in 1..num_nodes + 1 {
+            let node_stake = self.get_node_stake(i);
+            if node_stake > 0 {
+                result.push(MultiResult2::from((self.get_node_id_to_bls(i), node_stake)));
+            }
+        }
+        result.into()
+    }
+
+    /// Sum of the stake of all nodes.
+    /// Should match the total stake of the contract in the auction SC.
+    #[view(getTotalNodeStake)]
+    fn get_total_node_stake(&self) -> BigUint {
+        let num_nodes = self.get_num_nodes();
+        let mut total = BigUint::zero();
+        for i in 1..num_nodes + 1 {
+            total += self.get_node_stake(i);
+        }
+        total
+    }
+
+    /// Lists all nodes that are currently jailed.
+    #[view(getJailedNodes)]
+    fn get_jailed_nodes(&self) -> MultiResultVec<BLSKey> {
+        let num_nodes = self.get_num_nodes();
+        let mut result = Vec::new();
+        for i in 1..num_nodes + 1 {
+            if self.get_node_state(i) == NodeState::Jailed {
+                result.push(self.get_node_id_to_bls(i));
+            }
+        }
+        result.into()
+    }
+
+    /// Fines paid to unjail this node, since it was added to the contract.
+    #[storage_get("node_fines")]
+    fn get_node_fines_paid(&self, node_id: usize) -> BigUint;
+
+    #[storage_set("node_fines")]
+    fn set_node_fines_paid(&self, node_id: usize, node_fines_paid: &BigUint);
+
+    #[storage_clear("node_fines")]
+    fn clear_node_fines_paid(&self, node_id: usize);
+
+    #[view(getNodeFinesPaid)]
+    fn get_node_fines_paid_endpoint(&self, bls_key: BLSKey) -> OptionalResult<BigUint> {
+        let node_id = self.get_node_id(&bls_key);
+        if node_id == 0 {
+            OptionalResult::None
+        } else {
+            OptionalResult::Some(self.get_node_fines_paid(node_id))
+        }
+    }
+
+    /// Fines paid to unjail nodes, for all nodes.
+    #[view(getTotalFinesPaid)]
+    #[storage_get("total_fines")]
+    fn get_total_fines_paid(&self) -> BigUint;
+
+    #[storage_set("total_fines")]
+    fn set_total_fines_paid(&self, total_fines_paid: &BigUint);
+
     #[view(getNodeBlockNonceOfUnstake)]
     fn get_node_bl_nonce_of_unstake_endpoint(&self, bls_key: BLSKey) -> OptionalResult<u64> {
         let node_id = self.get_node_id(&bls_key);
@@ -97,16 +331,115 @@ pub trait NodeModule {
         }
     }
 
+    /// Short human-readable name of the node, for dashboards.
+    #[storage_get("node_label")]
+    fn get_node_label(&self, node_id: usize) -> BoxedBytes;
+
+    #[storage_set("node_label")]
+    fn set_node_label(&self, node_id: usize, label: &[u8]);
+
+    #[storage_clear("node_label")]
+    fn clear_node_label(&self, node_id: usize);
+
+    /// Where the node is hosted.
+    #[storage_get("node_region")]
+    fn get_node_region(&self, node_id: usize) -> BoxedBytes;
+
+    #[storage_set("node_region")]
+    fn set_node_region(&self, node_id: usize, region: &[u8]);
+
+    #[storage_clear("node_region")]
+    fn clear_node_region(&self, node_id: usize);
+
+    /// Arbitrary data attached to the node by the owner, e.g. identity or operator notes.
+    #[storage_get("node_metadata")]
+    fn get_node_metadata(&self, node_id: usize) -> BoxedBytes;
+
+    #[storage_set("node_metadata")]
+    fn set_node_metadata(&self, node_id: usize, metadata: &[u8]);
+
+    #[storage_clear("node_metadata")]
+    fn clear_node_metadata(&self, node_id: usize);
+
+    #[endpoint(setNodeInfo)]
+    fn set_node_info(
+        &self,
+        bls_key: BLSKey,
+        label: BoxedBytes,
+        region: BoxedBytes,
+        metadata: BoxedBytes,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can set node info");
+
+        let node_id = self.get_node_id(&bls_key);
+        require!(node_id != 0, "node not registered");
+        require!(label.len() <= MAX_NODE_LABEL_LENGTH, "node label too long");
+        require!(
+            region.len() <= MAX_NODE_REGION_LENGTH,
+            "node region too long"
+        );
+        require!(
+            metadata.len() <= MAX_NODE_METADATA_LENGTH,
+            "node metadata too long"
+        );
+
+        self.set_node_label(node_id, label.as_slice());
+        self.set_node_region(node_id, region.as_slice());
+        self.set_node_metadata(node_id, metadata.as_slice());
+        Ok(())
+    }
+
+    /// Yields, in order: node state, BLS signature, block nonce of unstake (0 if not in unbond period),
+    /// label, hosting region and metadata.
+    #[view(getNodeInfo)]
+    fn get_node_info(
+        &self,
+        bls_key: BLSKey,
+    ) -> OptionalResult<
+        MultiResult6<NodeState, BLSSignature, u64, BoxedBytes, BoxedBytes, BoxedBytes>,
+    > {
+        let node_id = self.get_node_id(&bls_key);
+        if node_id == 0 {
+            return OptionalResult::None;
+        }
+
+        let node_state = self.get_node_state(node_id);
+        let unbond_nonce = if let NodeState::UnBondPeriod { started } = node_state {
+            started
+        } else {
+            0
+        };
+        OptionalResult::Some(MultiResult6::from((
+            node_state,
+            self.get_node_signature(node_id),
+            unbond_nonce,
+            self.get_node_label(node_id),
+            self.get_node_region(node_id),
+            self.get_node_metadata(node_id),
+        )))
+    }
+
+    /// Registers new nodes, or re-adds removed ones.
+    /// Signatures are only checked to be well formed, the auction SC verifies them when the nodes are staked.
     #[endpoint(addNodes)]
     fn add_nodes(
         &self,
         #[var_args] bls_keys_signatures: VarArgs<MultiArg2<BLSKey, BLSSignature>>,
     ) -> SCResult<()> {
-        only_owner!(self, "only owner can add nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator can add nodes"
+        );
 
         let mut num_nodes = self.get_mut_num_nodes();
         for bls_sig_pair_arg in bls_keys_signatures.into_vec().into_iter() {
             let (bls_key, bls_sig) = bls_sig_pair_arg.into_tuple();
+            if !self.verify_bls_signature(&bls_key, &bls_sig) {
+                let mut err_msg = b"invalid signature for BLS key ".to_vec();
+                err_msg.extend_from_slice(&bls_key.to_vec());
+                return SCResult::Err(SCError::from(err_msg));
+            }
+
             let mut node_id = self.get_node_id(&bls_key);
             if node_id == 0 {
                 *num_nodes += 1;
@@ -127,7 +460,10 @@ pub trait NodeModule {
 
     #[endpoint(removeNodes)]
     fn remove_nodes(&self, #[var_args] bls_keys: VarArgs<BLSKey>) -> SCResult<()> {
-        only_owner!(self, "only owner can remove nodes");
+        require!(
+            self.operators().is_owner_or_operator(&self.get_caller()),
+            "only owner or operator can remove nodes"
+        );
 
         for bls_key in bls_keys.iter() {
             let node_id = self.get_node_id(bls_key);
@@ -142,12 +478,137 @@ pub trait NodeModule {
         Ok(())
     }
 
+    /// Replaces the signature of a registered node.
+    /// Not allowed while the node is waiting for an auction SC callback.
+    #[endpoint(setNodeSignature)]
+    fn set_node_signature_endpoint(&self, bls_key: BLSKey, bls_sig: BLSSignature) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can change node signatures");
+
+        let node_id = self.get_node_id(&bls_key);
+        require!(node_id != 0, "node not registered");
+        require!(
+            self.get_node_state(node_id).pending_since().is_none(),
+            "node is pending"
+        );
+        require!(
+            self.verify_bls_signature(&bls_key, &bls_sig),
+            "invalid signature for BLS key"
+        );
+
+        self.set_node_signature(node_id, bls_sig);
+        Ok(())
+    }
+
+    /// Replaces the BLS key of an inactive node.
+    /// The node keeps its id, so everything else recorded for it is preserved.
+    /// The old key is no longer registered afterwards, it can only be found in the rotation event.
+    #[endpoint(rotateNodeKey)]
+    fn rotate_node_key(
+        &self,
+        old_bls_key: BLSKey,
+        new_bls_key: BLSKey,
+        new_bls_sig: BLSSignature,
+    ) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can rotate node keys");
+
+        let node_id = self.get_node_id(&old_bls_key);
+        require!(node_id != 0, "node not registered");
+        require!(
+            self.get_node_state(node_id) == NodeState::Inactive,
+            "only inactive nodes can change key"
+        );
+        require!(
+            self.get_node_id(&new_bls_key) == 0,
+            "new BLS key already registered"
+        );
+        require!(
+            self.verify_bls_signature(&new_bls_key, &new_bls_sig),
+            "invalid signature for BLS key"
+        );
+
+        self.clear_node_bls_to_id(&old_bls_key);
+        self.set_node_bls_to_id(&new_bls_key, node_id);
+        self.set_node_id_to_bls(node_id, &new_bls_key);
+        self.set_node_signature(node_id, new_bls_sig);
+
+        self.rotate_node_key_event(&(old_bls_key, new_bls_key));
+        Ok(())
+    }
+
+    /// Permanently deletes all removed nodes and compacts the node ids,
+    /// so that iterating over nodes only goes through the nodes still in use.
+    /// The node with the highest id is moved into the slot of each deleted node.
+    /// Deleted nodes can be added again later, but they get a new id and their history (e.g. fines paid) is lost.
+    /// Not allowed while nodes are waiting for auction SC callbacks, since these refer to nodes by id.
+    #[endpoint(purgeRemovedNodes)]
+    fn purge_removed_nodes(&self) -> SCResult<()> {
+        only_delegation_owner!(self, "only owner can purge removed nodes");
+
+        let mut num_nodes = self.get_mut_num_nodes();
+        for node_id in 1..*num_nodes + 1 {
+            require!(
+                self.get_node_state(node_id).pending_since().is_none(),
+                "cannot purge nodes while some nodes are pending"
+            );
+        }
+
+        let mut node_id = 1;
+        while node_id <= *num_nodes {
+            if self.get_node_state(node_id) == NodeState::Removed {
+                self.clear_node_bls_to_id(&self.get_node_id_to_bls(node_id));
+                if node_id < *num_nodes {
+                    self.move_node(*num_nodes, node_id);
+                }
+                self.clear_node(*num_nodes);
+                *num_nodes -= 1;
+                // the node moved here is checked in the next iteration
+            } else {
+                node_id += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Copies all data of a node to a different id.
+    fn move_node(&self, from_node_id: usize, to_node_id: usize) {
+        let bls_key = self.get_node_id_to_bls(from_node_id);
+        self.set_node_bls_to_id(&bls_key, to_node_id);
+        self.set_node_id_to_bls(to_node_id, &bls_key);
+        self.set_node_signature(to_node_id, self.get_node_signature(from_node_id));
+        self.set_node_state(to_node_id, self.get_node_state(from_node_id));
+        self.set_node_callback_error(
+            to_node_id,
+            self.get_node_callback_error(from_node_id).as_slice(),
+        );
+        self.set_node_stake(to_node_id, &self.get_node_stake(from_node_id));
+        self.set_node_fines_paid(to_node_id, &self.get_node_fines_paid(from_node_id));
+        self.set_node_label(to_node_id, self.get_node_label(from_node_id).as_slice());
+        self.set_node_region(to_node_id, self.get_node_region(from_node_id).as_slice());
+        self.set_node_metadata(to_node_id, self.get_node_metadata(from_node_id).as_slice());
+    }
+
+    /// Deletes all data stored under a node id. Does not touch the BLS key to id mapping.
+    fn clear_node(&self, node_id: usize) {
+        self.clear_node_id_to_bls(node_id);
+        self.clear_node_signature(node_id);
+        self.clear_node_state(node_id);
+        self.clear_node_callback_error(node_id);
+        self.clear_node_stake(node_id);
+        self.clear_node_fines_paid(node_id);
+        self.clear_node_label(node_id);
+        self.clear_node_region(node_id);
+        self.clear_node_metadata(node_id);
+    }
+
+    /// Separates the nodes for which the auction SC reported an error.
+    /// Failed nodes are returned together with the error code they received.
     fn split_node_ids_by_err(
         &self,
         mut node_ids: Vec<usize>,
         node_status_args: VarArgs<BLSStatusMultiArg>,
-    ) -> (Vec<usize>, Vec<usize>) {
-        let mut failed_node_ids: Vec<usize> = Vec::new();
+    ) -> (Vec<usize>, Vec<(usize, i32)>) {
+        let mut failed_node_ids: Vec<(usize, i32)> = Vec::new();
         for arg in node_status_args.into_vec().into_iter() {
             let (bls_key, status) = arg.into_tuple();
             if status != 0 {
@@ -155,11 +616,16 @@ pub trait NodeModule {
                 // move node from ok nodes to failed ones
                 if let Some(pos) = node_ids.iter().position(|x| *x == node_id) {
                     node_ids.swap_remove(pos);
-                    failed_node_ids.push(node_id);
+                    failed_node_ids.push((node_id, status));
                 }
             }
         }
 
         (node_ids, failed_node_ids)
     }
+
+    /// Data: the old and the new BLS key of the node.
+    /// Event ids continue the ones in the delegation contract events module.
+    #[event("0x000000000000000000000000000000000000000000000000000000000000001c")]
+    fn rotate_node_key_event(&self, old_and_new_bls_keys: &(BLSKey, BLSKey));
 }
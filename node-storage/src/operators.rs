@@ -0,0 +1,95 @@
+imports!();
+
+/// The owner can appoint operators, who are allowed to manage nodes on its behalf:
+/// add, remove, stake, unstake, unbond and unjail them.
+/// Fee, cap and ownership changes remain reserved to the owner.
+/// Also keeps track of the owner itself, which can change with an ownership transfer.
+#[elrond_wasm_derive::module(OperatorsModuleImpl)]
+pub trait OperatorsModule {
+    #[storage_get("operators")]
+    fn get_operator_list(&self) -> Vec<Address>;
+
+    #[storage_set("operators")]
+    fn set_operator_list(&self, operators: &[Address]);
+
+    #[view(getOperators)]
+    fn get_operators(&self) -> MultiResultVec<Address> {
+        self.get_operator_list().into()
+    }
+
+    #[view(isOperator)]
+    fn is_operator(&self, address: &Address) -> bool {
+        self.get_operator_list().contains(address)
+    }
+
+    #[storage_get("delegation_owner")]
+    fn get_stored_delegation_owner(&self) -> Address;
+
+    #[storage_set("delegation_owner")]
+    fn set_delegation_owner(&self, delegation_owner: &Address);
+
+    #[storage_is_empty("delegation_owner")]
+    fn is_empty_delegation_owner(&self) -> bool;
+
+    /// The address allowed to call the owner-only endpoints.
+    /// It is the contract owner, until the ownership is transferred.
+    #[view(getDelegationOwner)]
+    fn get_delegation_owner(&self) -> Address {
+        if self.is_empty_delegation_owner() {
+            self.get_owner_address()
+        } else {
+            self.get_stored_delegation_owner()
+        }
+    }
+
+    fn is_delegation_owner(&self, address: &Address) -> bool {
+        *address == self.get_delegation_owner()
+    }
+
+    fn is_owner_or_operator(&self, address: &Address) -> bool {
+        self.is_delegation_owner(address) || self.is_operator(address)
+    }
+
+    #[endpoint(grantOperatorRole)]
+    fn grant_operator_role(&self, address: Address) -> SCResult<()> {
+        require!(
+            self.is_delegation_owner(&self.get_caller()),
+            "only owner can grant operator role"
+        );
+
+        let mut operators = self.get_operator_list();
+        require!(
+            !operators.contains(&address),
+            "address is already an operator"
+        );
+        operators.push(address.clone());
+        self.set_operator_list(operators.as_slice());
+
+        self.grant_operator_role_event(&address);
+        Ok(())
+    }
+
+    #[endpoint(revokeOperatorRole)]
+    fn revoke_operator_role(&self, address: Address) -> SCResult<()> {
+        require!(
+            self.is_delegation_owner(&self.get_caller()),
+            "only owner can revoke operator role"
+        );
+
+        let mut operators = self.get_operator_list();
+        let pos = operators.iter().position(|operator| *operator == address);
+        require!(pos.is_some(), "address is not an operator");
+        operators.swap_remove(pos.unwrap());
+        self.set_operator_list(operators.as_slice());
+
+        self.revoke_operator_role_event(&address);
+        Ok(())
+    }
+
+    /// Event ids continue the ones in the delegation contract events module.
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000015")]
+    fn grant_operator_role_event(&self, operator: &Address);
+
+    #[event("0x0000000000000000000000000000000000000000000000000000000000000016")]
+    fn revoke_operator_role_event(&self, operator: &Address);
+}
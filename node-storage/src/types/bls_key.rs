@@ -4,6 +4,7 @@ use elrond_wasm::{Box, Vec};
 // BLS keys have 96 bytes
 pub const BLS_KEY_BYTE_LENGTH: usize = 96;
 
+#[derive(Clone)]
 pub struct BLSKey(pub Box<[u8; BLS_KEY_BYTE_LENGTH]>);
 
 impl BLSKey {
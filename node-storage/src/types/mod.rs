@@ -7,3 +7,6 @@ pub use bls_sig::BLSSignature;
 pub use node_state::*;
 
 pub type BLSStatusMultiArg = elrond_wasm::MultiArg2<BLSKey, i32>;
+
+/// BLS key, together with the status reported by the auction SC ("staked", "unStaked", "notStaked").
+pub type BLSAuctionStatusMultiArg = elrond_wasm::MultiArg2<BLSKey, elrond_wasm::BoxedBytes>;
@@ -7,7 +7,8 @@ pub enum NodeState {
     Inactive,
 
     /// Stake call to auction sent, but callback not yet received.
-    PendingActivation,
+    /// `since` is the block nonce when the call was sent.
+    PendingActivation { since: u64 },
 
     /// Node stake was sent to the auction SC, but the transaction failed for the node.
     /// No longer used.
@@ -17,7 +18,8 @@ pub enum NodeState {
     Active,
 
     /// UnStake call to auction sent, but callback not yet received.
-    PendingDeactivation,
+    /// `since` is the block nonce when the call was sent.
+    PendingDeactivation { since: u64 },
 
     /// Same as Active, but no rewards are coming in.
     /// This is necessary for a period of time before the stake can be retrieved and unlocked.
@@ -25,23 +27,72 @@ pub enum NodeState {
 
     /// UnBond call to auction sent, but callback not yet received.
     /// `unbond_started` field is needed in case unbonding fails and the UnBondPeriod state needs to be restored.
-    PendingUnBond { unbond_started: u64 },
+    /// `since` is the block nonce when the call was sent.
+    PendingUnBond { unbond_started: u64, since: u64 },
 
     /// Node completely removed from the delegation contract.
     Removed,
+
+    /// Node was jailed by the protocol. Stake is still locked in the auction SC, but no rewards are coming in.
+    /// The node goes back to Active once it is unjailed.
+    Jailed,
 }
 
 impl NodeState {
     pub fn discriminant(&self) -> u8 {
         match self {
             NodeState::Inactive => 0,
-            NodeState::PendingActivation => 1,
+            NodeState::PendingActivation { .. } => 1,
             NodeState::ActivationFailed => 2,
             NodeState::Active => 3,
-            NodeState::PendingDeactivation => 4,
+            NodeState::PendingDeactivation { .. } => 4,
             NodeState::UnBondPeriod { .. } => 5,
             NodeState::PendingUnBond { .. } => 6,
             NodeState::Removed => 7,
+            NodeState::Jailed => 8,
+        }
+    }
+
+    /// The stable state a node returns to if its pending operation does not go through.
+    /// Yields None if the node is not waiting for an auction SC callback.
+    pub fn state_before_pending(&self) -> Option<NodeState> {
+        match self {
+            NodeState::PendingActivation { .. } => Some(NodeState::Inactive),
+            NodeState::PendingDeactivation { .. } => Some(NodeState::Active),
+            NodeState::PendingUnBond { unbond_started, .. } => Some(NodeState::UnBondPeriod {
+                started: *unbond_started,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Block nonce when the node started waiting for an auction SC callback.
+    /// Yields None if the node is not waiting for a callback.
+    pub fn pending_since(&self) -> Option<u64> {
+        match self {
+            NodeState::PendingActivation { since }
+            | NodeState::PendingDeactivation { since }
+            | NodeState::PendingUnBond { since, .. } => Some(*since),
+            _ => None,
+        }
+    }
+
+    /// Older contract versions saved pending states without the block nonce when they started.
+    /// Nodes that were left pending by such a version are decoded as pending since block 0.
+    fn legacy_pending_state(bytes: &[u8]) -> Option<NodeState> {
+        match bytes {
+            [1] => Some(NodeState::PendingActivation { since: 0 }),
+            [4] => Some(NodeState::PendingDeactivation { since: 0 }),
+            [6, unbond_started_bytes @ ..] if unbond_started_bytes.len() == 8 => {
+                let mut unbond_started_slice = unbond_started_bytes;
+                u64::dep_decode(&mut unbond_started_slice)
+                    .ok()
+                    .map(|unbond_started| NodeState::PendingUnBond {
+                        unbond_started,
+                        since: 0,
+                    })
+            }
+            _ => None,
         }
     }
 }
@@ -52,8 +103,9 @@ impl NestedEncode for NodeState {
             NodeState::Inactive => {
                 dest.push_byte(0);
             }
-            NodeState::PendingActivation => {
+            NodeState::PendingActivation { since } => {
                 dest.push_byte(1);
+                since.dep_encode(dest)?;
             }
             NodeState::ActivationFailed => {
                 dest.push_byte(2);
@@ -61,20 +113,28 @@ impl NestedEncode for NodeState {
             NodeState::Active => {
                 dest.push_byte(3);
             }
-            NodeState::PendingDeactivation => {
+            NodeState::PendingDeactivation { since } => {
                 dest.push_byte(4);
+                since.dep_encode(dest)?;
             }
             NodeState::UnBondPeriod { started } => {
                 dest.push_byte(5);
                 started.dep_encode(dest)?;
             }
-            NodeState::PendingUnBond { unbond_started } => {
+            NodeState::PendingUnBond {
+                unbond_started,
+                since,
+            } => {
                 dest.push_byte(6);
                 unbond_started.dep_encode(dest)?;
+                since.dep_encode(dest)?;
             }
             NodeState::Removed => {
                 dest.push_byte(7);
             }
+            NodeState::Jailed => {
+                dest.push_byte(8);
+            }
         }
         Ok(())
     }
@@ -90,8 +150,9 @@ impl NestedEncode for NodeState {
             NodeState::Inactive => {
                 dest.push_byte(0);
             }
-            NodeState::PendingActivation => {
+            NodeState::PendingActivation { since } => {
                 dest.push_byte(1);
+                since.dep_encode_or_exit(dest, c.clone(), exit);
             }
             NodeState::ActivationFailed => {
                 dest.push_byte(2);
@@ -99,20 +160,28 @@ impl NestedEncode for NodeState {
             NodeState::Active => {
                 dest.push_byte(3);
             }
-            NodeState::PendingDeactivation => {
+            NodeState::PendingDeactivation { since } => {
                 dest.push_byte(4);
+                since.dep_encode_or_exit(dest, c.clone(), exit);
             }
             NodeState::UnBondPeriod { started } => {
                 dest.push_byte(5);
                 started.dep_encode_or_exit(dest, c.clone(), exit);
             }
-            NodeState::PendingUnBond { unbond_started } => {
+            NodeState::PendingUnBond {
+                unbond_started,
+                since,
+            } => {
                 dest.push_byte(6);
                 unbond_started.dep_encode_or_exit(dest, c.clone(), exit);
+                since.dep_encode_or_exit(dest, c.clone(), exit);
             }
             NodeState::Removed => {
                 dest.push_byte(7);
             }
+            NodeState::Jailed => {
+                dest.push_byte(8);
+            }
         }
     }
 }
@@ -139,17 +208,23 @@ impl NestedDecode for NodeState {
         let discriminant = input.read_byte()?;
         match discriminant {
             0 => Ok(NodeState::Inactive),
-            1 => Ok(NodeState::PendingActivation),
+            1 => Ok(NodeState::PendingActivation {
+                since: u64::dep_decode(input)?,
+            }),
             2 => Ok(NodeState::ActivationFailed),
             3 => Ok(NodeState::Active),
-            4 => Ok(NodeState::PendingDeactivation),
+            4 => Ok(NodeState::PendingDeactivation {
+                since: u64::dep_decode(input)?,
+            }),
             5 => Ok(NodeState::UnBondPeriod {
                 started: u64::dep_decode(input)?,
             }),
             6 => Ok(NodeState::PendingUnBond {
                 unbond_started: u64::dep_decode(input)?,
+                since: u64::dep_decode(input)?,
             }),
             7 => Ok(NodeState::Removed),
+            8 => Ok(NodeState::Jailed),
             _ => Err(DecodeError::INVALID_VALUE),
         }
     }
@@ -163,17 +238,23 @@ impl NestedDecode for NodeState {
         let discriminant = input.read_byte_or_exit(c.clone(), exit);
         match discriminant {
             0 => NodeState::Inactive,
-            1 => NodeState::PendingActivation,
+            1 => NodeState::PendingActivation {
+                since: u64::dep_decode_or_exit(input, c.clone(), exit),
+            },
             2 => NodeState::ActivationFailed,
             3 => NodeState::Active,
-            4 => NodeState::PendingDeactivation,
+            4 => NodeState::PendingDeactivation {
+                since: u64::dep_decode_or_exit(input, c.clone(), exit),
+            },
             5 => NodeState::UnBondPeriod {
                 started: u64::dep_decode_or_exit(input, c.clone(), exit),
             },
             6 => NodeState::PendingUnBond {
                 unbond_started: u64::dep_decode_or_exit(input, c.clone(), exit),
+                since: u64::dep_decode_or_exit(input, c.clone(), exit),
             },
             7 => NodeState::Removed,
+            8 => NodeState::Jailed,
             _ => exit(c, DecodeError::INVALID_VALUE),
         }
     }
@@ -181,7 +262,11 @@ impl NestedDecode for NodeState {
 
 impl TopDecode for NodeState {
     fn top_decode<I: TopDecodeInput>(input: I) -> Result<Self, DecodeError> {
-        top_decode_from_nested(input)
+        let bytes = input.into_boxed_slice_u8();
+        if let Some(legacy_state) = NodeState::legacy_pending_state(&bytes) {
+            return Ok(legacy_state);
+        }
+        top_decode_from_nested(bytes)
     }
 
     fn top_decode_or_exit<I: TopDecodeInput, ExitCtx: Clone>(
@@ -189,6 +274,58 @@ impl TopDecode for NodeState {
         c: ExitCtx,
         exit: fn(ExitCtx, DecodeError) -> !,
     ) -> Self {
-        top_decode_from_nested_or_exit(input, c, exit)
+        let bytes = input.into_boxed_slice_u8();
+        if let Some(legacy_state) = NodeState::legacy_pending_state(&bytes) {
+            return legacy_state;
+        }
+        top_decode_from_nested_or_exit(bytes, c, exit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use elrond_wasm::elrond_codec::test_util::*;
+
+    fn check_node_state_codec(node_state: NodeState) {
+        let top_encoded = check_top_encode(&node_state);
+        assert_eq!(check_top_decode::<NodeState>(&top_encoded[..]), node_state);
+
+        let dep_encoded = check_dep_encode(&node_state);
+        assert_eq!(check_dep_decode::<NodeState>(&dep_encoded[..]), node_state);
+    }
+
+    #[test]
+    fn test_node_state_serialization() {
+        check_node_state_codec(NodeState::Inactive);
+        check_node_state_codec(NodeState::PendingActivation { since: 5 });
+        check_node_state_codec(NodeState::Active);
+        check_node_state_codec(NodeState::PendingDeactivation { since: 6 });
+        check_node_state_codec(NodeState::UnBondPeriod { started: 7 });
+        check_node_state_codec(NodeState::PendingUnBond {
+            unbond_started: 7,
+            since: 8,
+        });
+        check_node_state_codec(NodeState::Removed);
+        check_node_state_codec(NodeState::Jailed);
+    }
+
+    #[test]
+    fn test_legacy_pending_state_deserialization() {
+        assert_eq!(
+            check_top_decode::<NodeState>(&[1u8][..]),
+            NodeState::PendingActivation { since: 0 }
+        );
+        assert_eq!(
+            check_top_decode::<NodeState>(&[4u8][..]),
+            NodeState::PendingDeactivation { since: 0 }
+        );
+        assert_eq!(
+            check_top_decode::<NodeState>(&[6u8, 0, 0, 0, 0, 0, 0, 0, 7][..]),
+            NodeState::PendingUnBond {
+                unbond_started: 7,
+                since: 0,
+            }
+        );
     }
 }
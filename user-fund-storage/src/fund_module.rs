@@ -46,6 +46,14 @@ pub trait FundModule {
         fund_type: FundType,
     ) -> mut_storage!(FundsListInfo<BigUint>);
 
+    #[storage_set("fuser")]
+    fn set_fund_list_by_user(
+        &self,
+        user_id: usize,
+        fund_type: FundType,
+        funds_list_info: &FundsListInfo<BigUint>,
+    );
+
     /// For testing; please do not use in production.
     /// Goes through all fund items, ignores indexes.
     fn query_sum_all_funds_brute_force<F>(&self, filter: F) -> BigUint
@@ -507,6 +515,22 @@ pub trait FundModule {
         total_transformed
     }
 
+    /// Moves all funds of a type from one user to another, who must not have funds of that type.
+    /// The fund items keep their place in the type list, so the queues are not affected.
+    fn move_all_to_user(&self, from_user_id: usize, to_user_id: usize, fund_type: FundType) {
+        let user_list = self.get_fund_list_by_user(from_user_id, fund_type);
+        let mut id = user_list.first;
+
+        while id > 0 {
+            let mut fund_item = self.get_mut_fund_by_id(id);
+            fund_item.user_id = to_user_id;
+            id = fund_item.user_list_next;
+        }
+
+        self.set_fund_list_by_user(to_user_id, fund_type, &user_list);
+        self.set_fund_list_by_user(from_user_id, fund_type, &FundsListInfo::zero_value());
+    }
+
     fn destroy_all_for_user(&self, user_id: usize, source_type: FundType) -> BigUint {
         let user_list = self.get_fund_list_by_user(user_id, source_type);
         let mut id = user_list.first;
@@ -25,6 +25,14 @@ pub trait FundTransformationsModule {
             .destroy_all_for_user(user_id, FundType::WithdrawOnly)
     }
 
+    /// Moves all funds of a user to another user, who must not have any funds yet.
+    fn move_all_user_funds(&self, from_user_id: usize, to_user_id: usize) {
+        for &fund_type in FundType::ALL_TYPES.iter() {
+            self.fund_module()
+                .move_all_to_user(from_user_id, to_user_id, fund_type);
+        }
+    }
+
     fn swap_user_active_to_unstaked(&self, unstake_user_id: usize, amount: &mut BigUint) {
         let current_bl_nonce = self.get_block_nonce();
         let _ = self.fund_module().split_convert_max_by_user(
@@ -40,16 +48,22 @@ pub trait FundTransformationsModule {
         );
     }
 
-    fn swap_waiting_to_active<I: Fn() -> bool>(
+    /// `before_swap` is called with the user id before each of their funds gets swapped,
+    /// after the interrupt check.
+    fn swap_waiting_to_active<I: Fn() -> bool, B: FnMut(usize)>(
         &self,
         remaining: &mut BigUint,
         interrupt: I,
+        mut before_swap: B,
     ) -> Vec<usize> {
         self.fund_module().split_convert_max_by_type(
             Some(remaining),
             FundType::Waiting,
             SwapDirection::Forwards,
-            |_, _| Some(FundDescription::Active),
+            |user_id, _| {
+                before_swap(user_id);
+                Some(FundDescription::Active)
+            },
             interrupt,
             false,
         )
@@ -103,24 +117,28 @@ pub trait FundTransformationsModule {
         );
     }
 
-    fn swap_active_to_deferred_payment<I: Fn() -> bool>(
+    /// `before_swap` is called with the user id before each of their funds gets swapped,
+    /// after the interrupt check.
+    fn swap_active_to_deferred_payment<I: Fn() -> bool, B: FnMut(usize)>(
         &self,
         remaining: &mut BigUint,
         interrupt: I,
-    ) {
+        mut before_swap: B,
+    ) -> Vec<usize> {
         let current_bl_nonce = self.get_block_nonce();
-        let _ = self.fund_module().split_convert_max_by_type(
+        self.fund_module().split_convert_max_by_type(
             Some(remaining),
             FundType::Active,
             SwapDirection::Backwards,
-            |_, _| {
+            |user_id, _| {
+                before_swap(user_id);
                 Some(FundDescription::DeferredPayment {
                     created: current_bl_nonce,
                 })
             },
             interrupt,
             false,
-        );
+        )
     }
 
     fn swap_eligible_deferred_to_withdraw(
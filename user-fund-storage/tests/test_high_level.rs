@@ -93,7 +93,7 @@ fn test_full_cycle_1() {
 
     // Waiting -> Active
     let mut amount = RustBigUint::from(5000u32);
-    let affected_users = transf_module.swap_waiting_to_active(&mut amount, || false);
+    let affected_users = transf_module.swap_waiting_to_active(&mut amount, || false, |_| {});
     assert_eq!(affected_users, vec![user_id]);
     assert_eq!(amount, RustBigUint::zero());
 
@@ -223,3 +223,44 @@ fn test_full_cycle_1() {
         fund_module.count_fund_items_by_user_type(user_id, FundType::WithdrawOnly, |_| true)
     );
 }
+
+#[test]
+fn test_move_all_user_funds() {
+    let transf_module = FundTransformationsModuleImpl::new(TxContext::dummy());
+    let fund_module = transf_module.fund_module();
+
+    transf_module.create_waiting(1, 3000u32.into());
+    transf_module.create_waiting(2, 2000u32.into());
+    let mut amount = RustBigUint::from(1000u32);
+    let _ = transf_module.swap_waiting_to_active(&mut amount, || false, |_| {});
+
+    transf_module.move_all_user_funds(1, 3);
+
+    fund_module_check::check_consistency(&fund_module, 3);
+    assert_eq!(
+        RustBigUint::from(0u32),
+        fund_module.query_sum_funds_by_user_type(1, FundType::Waiting, |_| true)
+    );
+    assert_eq!(
+        RustBigUint::from(0u32),
+        fund_module.query_sum_funds_by_user_type(1, FundType::Active, |_| true)
+    );
+    assert_eq!(
+        RustBigUint::from(2000u32),
+        fund_module.query_sum_funds_by_user_type(3, FundType::Waiting, |_| true)
+    );
+    assert_eq!(
+        RustBigUint::from(1000u32),
+        fund_module.query_sum_funds_by_user_type(3, FundType::Active, |_| true)
+    );
+    assert_eq!(
+        RustBigUint::from(4000u32),
+        fund_module.query_sum_funds_by_type(FundType::Waiting, |_, _| true)
+    );
+
+    // the moved funds keep their place in the queue
+    let mut amount = RustBigUint::from(2000u32);
+    let affected_users = transf_module.swap_waiting_to_active(&mut amount, || false, |_| {});
+    assert_eq!(affected_users, vec![3]);
+    fund_module_check::check_consistency(&fund_module, 3);
+}